@@ -5,9 +5,13 @@ use inputbot::{
 };
 use std::thread;
 // This example demonstrates blocking input with conditional flags, such as another key being
-// pressed or toggled. This example currently does not work on Linux.
+// pressed or toggled. On Linux, only keyboard input can be blocked.
 
 fn main() {
+    // On Linux, keyboards have to be grabbed for their input to be blocked.
+    #[cfg(target_os = "linux")]
+    inputbot::set_grab_keyboards(true);
+
     // Block the A key when left shift is held. Note: callbacks for blockable binds won't be
     // executed in new threads, so for long-running processes create new threads inside the callback
     // if needed.
@@ -0,0 +1,12 @@
+/// This example demonstrates loading binds from a config file, which is applied again
+/// whenever it changes. Edit examples/config.toml while it runs.
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    inputbot::watch_config(
+        concat!(env!("CARGO_MANIFEST_DIR"), "/examples/config.toml"),
+        |err| eprintln!("config not reloaded: {err}"),
+    )?;
+
+    // Call this to start listening for bound inputs.
+    inputbot::handle_input_events(false);
+    Ok(())
+}
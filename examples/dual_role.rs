@@ -0,0 +1,27 @@
+use inputbot::{DualRoleMode, DualRoleOptions, KeybdKey::*};
+use std::time::Duration;
+
+/// This example demonstrates dual-role keys, which act as one key when tapped and as
+/// another while held.
+fn main() {
+    // On Linux, keyboards have to be grabbed for the original key events to be hidden.
+    #[cfg(target_os = "linux")]
+    inputbot::set_grab_keyboards(true);
+
+    // Tap CapsLock for Escape, hold it for Control.
+    CapsLockKey.dual_role(EscapeKey, LControlKey);
+
+    // Tap Space for a space, hold it for Shift.
+    SpaceKey.dual_role(SpaceKey, LShiftKey);
+
+    // Keys typed quickly while Space is down would otherwise only count as shifted once
+    // Space has been held for the whole tapping term. With permissive hold, pressing and
+    // releasing another key while Space is down shifts it right away.
+    inputbot::set_dual_role_options(DualRoleOptions {
+        tapping_term: Duration::from_millis(200),
+        mode: DualRoleMode::PermissiveHold,
+    });
+
+    // Call this to start listening for bound inputs.
+    inputbot::handle_input_events(false);
+}
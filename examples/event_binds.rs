@@ -0,0 +1,22 @@
+use inputbot::{KeybdKey::*, Modifiers, MouseButton::*};
+
+/// This example demonstrates binds which receive the event that fired them.
+fn main() {
+    // Print everything known about presses of the A key.
+    AKey.bind_event(|event| {
+        println!(
+            "{:?} {:?} at {:?} from {:?}, modifiers {:?}",
+            event.input, event.event, event.timestamp, event.device, event.modifiers
+        );
+    });
+
+    // Only react to Ctrl + left click, using the modifiers held when the button was pressed.
+    LeftButton.bind_event(|event| {
+        if event.modifiers.intersects(Modifiers::CONTROL) {
+            println!("Ctrl + click");
+        }
+    });
+
+    // Call this to start listening for bound inputs.
+    inputbot::handle_input_events(false);
+}
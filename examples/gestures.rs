@@ -0,0 +1,31 @@
+use inputbot::{Gesture, KeySequence, KeybdKey::*, MouseButton::*};
+
+/// This example demonstrates binding different actions to tapping, double tapping and
+/// holding the same key or mouse button.
+fn main() {
+    // Tap F9 to type a greeting, or double tap it to type a farewell. Note: while a key has
+    // gesture binds it is blocked, so F9 itself won't reach other programs.
+    F9Key.bind_gesture(Gesture::Tap, || KeySequence("Hello!").send());
+    F9Key.bind_gesture(Gesture::DoubleTap, || KeySequence("Goodbye!").send());
+
+    // Holding F9 fires as soon as the key has been down for long enough.
+    F9Key.bind_gesture(Gesture::Hold, || println!("F9 held"));
+
+    // Gestures work for mouse buttons too. Double click the middle button to double click
+    // the left button.
+    MiddleButton.bind_gesture(Gesture::DoubleTap, || {
+        for _ in 0..2 {
+            LeftButton.press();
+            LeftButton.release();
+        }
+    });
+
+    // Gestures are told apart with the default timing, which can be changed like so:
+    //     inputbot::set_gesture_timing(inputbot::GestureTiming {
+    //         hold: std::time::Duration::from_millis(300),
+    //         double_tap: std::time::Duration::from_millis(200),
+    //     });
+
+    // Call this to start listening for bound inputs.
+    inputbot::handle_input_events(false);
+}
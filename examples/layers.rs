@@ -0,0 +1,51 @@
+use inputbot::{KeybdKey::*, Layer, BASE_LAYER};
+
+/// This example demonstrates layers, which change what keys do while they are active.
+fn main() {
+    // On Linux, keyboards have to be grabbed for the original key events to be hidden.
+    #[cfg(target_os = "linux")]
+    inputbot::set_grab_keyboards(true);
+
+    // The base layer is always active, so keys which switch layers go here. Holding CapsLock
+    // activates the navigation layer, RAlt toggles the numpad layer on and off, and RControl
+    // activates the numpad layer for the next key only.
+    inputbot::define_layer(
+        BASE_LAYER,
+        Layer::new()
+            .momentary(CapsLockKey, "nav")
+            .toggle(RAltKey, "numpad")
+            .one_shot(RControlKey, "numpad"),
+    );
+
+    // Vim-style arrow keys. Keys the layer doesn't define keep working as usual.
+    inputbot::define_layer(
+        "nav",
+        Layer::new()
+            .map(HKey, LeftKey)
+            .map(JKey, DownKey)
+            .map(KKey, UpKey)
+            .map(LKey, RightKey)
+            .bind(QKey, || {
+                println!("Active layers: {:?}", inputbot::active_layers())
+            }),
+    );
+
+    inputbot::define_layer(
+        "numpad",
+        Layer::new()
+            .map(MKey, Numpad1Key)
+            .map(CommaKey, Numpad2Key)
+            .map(PeriodKey, Numpad3Key)
+            .map(JKey, Numpad4Key)
+            .map(KKey, Numpad5Key)
+            .map(LKey, Numpad6Key)
+            .map(UKey, Numpad7Key)
+            .map(IKey, Numpad8Key)
+            .map(OKey, Numpad9Key)
+            .map(SpaceKey, Numpad0Key)
+            .block(PKey),
+    );
+
+    // Call this to start listening for bound inputs.
+    inputbot::handle_input_events(false);
+}
@@ -0,0 +1,41 @@
+/// This example demonstrates turning a secondary keyboard into a macro pad on Linux, while
+/// the main keyboard keeps working as usual.
+#[cfg(target_os = "linux")]
+fn main() {
+    use inputbot::{DeviceFilter, KeybdKey::*};
+
+    // Pass the name of the macro pad as the first argument. Without one, list the devices.
+    let Some(name) = std::env::args().nth(1) else {
+        for device in inputbot::devices() {
+            println!(
+                "{}: {} ({:04x}:{:04x}) {:?}",
+                device.sysname,
+                device.name,
+                device.vendor_id,
+                device.product_id,
+                device.capabilities
+            );
+        }
+        return;
+    };
+
+    // Keyboards have to be grabbed for the keys of the macro pad to be hidden.
+    inputbot::set_grab_keyboards(true);
+
+    // The binds below match the macro pad by name, so they apply again when it's plugged back
+    // in. Other configuration can be reapplied from a hotplug listener.
+    inputbot::set_hotplug_listener(|event| println!("{event:?}"));
+
+    let pad = DeviceFilter::Name(name);
+    Numpad1Key.block_bind_on_device(pad.clone(), || println!("Macro 1"));
+    Numpad2Key.block_bind_on_device(pad.clone(), || println!("Macro 2"));
+    Numpad3Key.block_bind_on_device(pad, || println!("Macro 3"));
+
+    // Call this to start listening for bound inputs.
+    inputbot::handle_input_events(false);
+}
+
+#[cfg(not(target_os = "linux"))]
+fn main() {
+    println!("Device binds are only supported on Linux.");
+}
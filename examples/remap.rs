@@ -0,0 +1,21 @@
+use inputbot::KeybdKey::*;
+
+/// This example demonstrates remapping keys for all applications.
+fn main() {
+    // On Linux, keyboards have to be grabbed for the original key events to be hidden.
+    #[cfg(target_os = "linux")]
+    inputbot::set_grab_keyboards(true);
+
+    // Make CapsLock another Control key.
+    CapsLockKey.remap(LControlKey);
+
+    // Swap the backquote and escape keys. Keys pressed by inputbot aren't remapped again, so
+    // this doesn't loop.
+    inputbot::set_remaps([(BackquoteKey, EscapeKey), (EscapeKey, BackquoteKey)]);
+
+    // Note that `set_remaps` replaced the CapsLock remap above, so add it back.
+    CapsLockKey.remap(LControlKey);
+
+    // Call this to start listening for bound inputs.
+    inputbot::handle_input_events(false);
+}
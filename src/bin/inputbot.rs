@@ -0,0 +1,407 @@
+use inputbot::{
+    HotkeySequence, Input, InputEvent, KeySequence, KeybdKey, MouseButton, MouseCursor, MouseWheel,
+};
+use std::{
+    process::ExitCode,
+    thread::sleep,
+    time::{Duration, UNIX_EPOCH},
+};
+
+const USAGE: &str = "\
+usage: inputbot <command> [<args>]
+
+commands:
+  type <text>                 type the text
+  key <hotkey>...             send hotkeys like 'ctrl+c' or 'ctrl+x ctrl+s'
+  keydown <key>               press a key and leave it down
+  keyup <key>                 release a key
+  click <button> [<count>]    click a mouse button like 'left', 'right' or 'middle'
+  move [--relative] <x> <y>   move the mouse cursor to a position, or by an offset
+  scroll <notches>            scroll down, or up if negative
+  wait <duration>             wait like '200ms', '1.5s' or '200' (milliseconds)
+  watch [--json]              print key and button events until interrupted, as text or
+                              as one JSON object per line (with the serde feature)
+  diagnostics                 check permissions and services needed on Linux";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let Some((command, args)) = args.split_first() else {
+        eprintln!("{USAGE}");
+        return ExitCode::from(2);
+    };
+    let result = match command.as_str() {
+        "diagnostics" => return diagnostics(),
+        "wait" => wait(args),
+        "watch" => watch(args),
+        "type" | "key" | "keydown" | "keyup" | "click" | "move" | "scroll" => send(command, args),
+        _ => {
+            eprintln!("{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("inputbot {command}: {err}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+// How long the system takes to pick up the fake device on Linux. Input sent before that,
+// or still queued when the process exits and the device goes away, is lost.
+#[cfg(target_os = "linux")]
+const DEVICE_SETTLE_TIME: Duration = Duration::from_millis(200);
+
+// Input to send, parsed from the arguments of a command.
+#[derive(Debug, PartialEq)]
+enum Action {
+    Type(String),
+    Key(HotkeySequence),
+    KeyDown(KeybdKey),
+    KeyUp(KeybdKey),
+    Click(MouseButton, u32),
+    Move { relative: bool, x: i32, y: i32 },
+    Scroll(i32),
+}
+
+fn send(command: &str, args: &[String]) -> Result<(), String> {
+    // Arguments are checked before the fake device is created, so that mistakes fail fast.
+    let action = parse_action(command, args)?;
+
+    #[cfg(target_os = "linux")]
+    {
+        inputbot::init_device();
+        sleep(DEVICE_SETTLE_TIME);
+    }
+    let result = action.send();
+    #[cfg(target_os = "linux")]
+    sleep(DEVICE_SETTLE_TIME);
+    result
+}
+
+fn parse_action(command: &str, args: &[String]) -> Result<Action, String> {
+    Ok(match command {
+        "type" => Action::Type(args.join(" ")),
+        "key" => Action::Key(args.join(" ").parse().map_err(|err| format!("{err}"))?),
+        "keydown" => Action::KeyDown(parse_key(args)?),
+        "keyup" => Action::KeyUp(parse_key(args)?),
+        "click" => {
+            let (button, count) = match args {
+                [button] => (button, 1),
+                [button, count] => (button, parse_number::<u32>(count)?),
+                _ => return Err("expected a button and an optional count".to_owned()),
+            };
+            Action::Click(button.parse().map_err(|err| format!("{err}"))?, count)
+        }
+        "move" => {
+            let (relative, x, y) = match args {
+                [flag, x, y] if flag == "--relative" || flag == "-r" => (true, x, y),
+                [x, y] => (false, x, y),
+                _ => return Err("expected a position like '100 200'".to_owned()),
+            };
+            Action::Move {
+                relative,
+                x: parse_number(x)?,
+                y: parse_number(y)?,
+            }
+        }
+        "scroll" => {
+            let [notches] = args else {
+                return Err("expected a number of notches".to_owned());
+            };
+            Action::Scroll(parse_number(notches)?)
+        }
+        _ => unreachable!(),
+    })
+}
+
+impl Action {
+    fn send(self) -> Result<(), String> {
+        match self {
+            Action::Type(text) => KeySequence(&text).send(),
+            Action::Key(hotkeys) => hotkeys.send(),
+            Action::KeyDown(key) => key.press(),
+            Action::KeyUp(key) => key.release(),
+            Action::Click(button, count) => {
+                for _ in 0..count {
+                    button.press();
+                    button.release();
+                }
+            }
+            Action::Move {
+                relative: true,
+                x,
+                y,
+            } => MouseCursor::move_rel(x, y),
+            Action::Move {
+                relative: false,
+                x,
+                y,
+            } => return MouseCursor::try_move_abs(x, y).map_err(|err| err.to_string()),
+            // Windows scrolls up for positive amounts, while Linux scrolls down.
+            Action::Scroll(notches) if cfg!(target_os = "windows") => {
+                MouseWheel::scroll_ver(-notches)
+            }
+            Action::Scroll(notches) => MouseWheel::scroll_ver(notches),
+        }
+        Ok(())
+    }
+}
+
+fn wait(args: &[String]) -> Result<(), String> {
+    let [duration] = args else {
+        return Err("expected a duration".to_owned());
+    };
+    sleep(parse_duration(duration)?);
+    Ok(())
+}
+
+fn parse_key(args: &[String]) -> Result<KeybdKey, String> {
+    let [key] = args else {
+        return Err("expected a key".to_owned());
+    };
+    key.parse().map_err(|err| format!("{err}"))
+}
+
+fn parse_number<T: std::str::FromStr>(s: &str) -> Result<T, String> {
+    s.parse().map_err(|_| format!("'{s}' isn't a valid number"))
+}
+
+// Parses durations like "200ms", "1.5s" or "200", which is in milliseconds.
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    let (number, unit) = match s.strip_suffix("ms") {
+        Some(number) => (number, 0.001),
+        None => match s.strip_suffix('s') {
+            Some(number) => (number, 1.0),
+            None => (s, 0.001),
+        },
+    };
+    number
+        .parse::<f64>()
+        .ok()
+        .and_then(|number| Duration::try_from_secs_f64(number * unit).ok())
+        .ok_or_else(|| format!("'{s}' isn't a valid duration"))
+}
+
+fn watch(args: &[String]) -> Result<(), String> {
+    match args {
+        [] => inputbot::set_event_listener(|event| println!("{}", event_text(event))),
+        #[cfg(feature = "serde")]
+        [flag] if flag == "--json" => {
+            inputbot::set_event_listener(|event| println!("{}", event_json(event)))
+        }
+        #[cfg(not(feature = "serde"))]
+        [flag] if flag == "--json" => {
+            return Err("JSON needs inputbot built with the serde feature".to_owned())
+        }
+        _ => return Err("expected '--json' or nothing".to_owned()),
+    }
+    inputbot::handle_input_events(false);
+    Ok(())
+}
+
+// Like "1697712345.123 Press LeftShift (evdev 42, keysym 0xFFE1) LSHIFT  AT keyboard".
+fn event_text(event: &InputEvent) -> String {
+    let codes = raw_codes(event.input)
+        .iter()
+        .map(|&(name, code)| format!("{name} {code:#X}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let mut text = format!(
+        "{} {:?} {} ({codes})",
+        timestamp(event),
+        event.event,
+        event.input
+    );
+    let modifiers: Vec<&str> = event.modifiers.iter_names().map(|(name, _)| name).collect();
+    if !modifiers.is_empty() {
+        text += &format!(" {}", modifiers.join("+"));
+    }
+    if event.is_injected {
+        text += " injected";
+    }
+    if let Some(device) = &event.device {
+        text += &format!("  {device}");
+    }
+    text
+}
+
+// The event as serialized by inputbot, followed by its raw codes, like
+// `{"time":1697712345.123,"event":"press","key":"LeftShift",...,"evdev":42,"keysym":65505}`.
+#[cfg(feature = "serde")]
+fn event_json(event: &InputEvent) -> String {
+    #[derive(serde::Serialize)]
+    struct WatchedEvent<'a> {
+        #[serde(flatten)]
+        event: &'a InputEvent,
+        #[serde(flatten)]
+        codes: std::collections::BTreeMap<&'static str, u64>,
+    }
+
+    serde_json::to_string(&WatchedEvent {
+        event,
+        codes: raw_codes(event.input).into_iter().collect(),
+    })
+    .unwrap()
+}
+
+// Seconds since the Unix epoch, with milliseconds.
+fn timestamp(event: &InputEvent) -> String {
+    let time = event
+        .timestamp
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    format!("{}.{:03}", time.as_secs(), time.subsec_millis())
+}
+
+// The codes the platform identifies the key or button with.
+fn raw_codes(input: Input) -> Vec<(&'static str, u64)> {
+    let mut codes = Vec::new();
+    match input {
+        Input::Keybd(key) => {
+            let physical = key.physical_key();
+            #[cfg(target_os = "windows")]
+            {
+                codes.push(("vk", u64::from(key)));
+                if let Some((scancode, extended)) = physical.and_then(|key| key.scancode()) {
+                    let prefix = if extended { 0xE000 } else { 0 };
+                    codes.push(("scancode", u64::from(prefix | scancode)));
+                }
+            }
+            #[cfg(target_os = "linux")]
+            {
+                if let Some(physical) = physical {
+                    codes.push(("evdev", u64::from(physical.0)));
+                }
+                if let Some(keysym) = key.keysym() {
+                    codes.push(("keysym", keysym));
+                }
+            }
+        }
+        Input::Mouse(button) => {
+            #[cfg(target_os = "windows")]
+            codes.push(("vk", u64::from(u32::from(button))));
+            #[cfg(target_os = "linux")]
+            if let Some(code) = button.evdev_code() {
+                codes.push(("evdev", u64::from(code)));
+            }
+        }
+    }
+    codes
+}
+
+#[cfg(target_os = "linux")]
+fn diagnostics() -> ExitCode {
+    let report = inputbot::diagnostics();
+    print!("{report}");
+    if report.is_ok() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn diagnostics() -> ExitCode {
+    println!("Nothing to check, inputbot needs no extra permissions on this platform.");
+    ExitCode::SUCCESS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn durations_parse() {
+        assert_eq!(parse_duration("200ms"), Ok(Duration::from_millis(200)));
+        assert_eq!(parse_duration("1.5s"), Ok(Duration::from_millis(1500)));
+        assert_eq!(parse_duration("200"), Ok(Duration::from_millis(200)));
+        assert_eq!(parse_duration("0"), Ok(Duration::ZERO));
+        for invalid in ["", "ms", "-1s", "1.5m", "fast"] {
+            assert!(parse_duration(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn commands_parse() {
+        assert_eq!(
+            parse_action("type", &args(&["hello", "world"])),
+            Ok(Action::Type("hello world".to_owned()))
+        );
+        assert_eq!(
+            parse_action("key", &args(&["ctrl+x", "ctrl+s"])),
+            Ok(Action::Key("ctrl+x ctrl+s".parse().unwrap()))
+        );
+        assert!(parse_action("key", &args(&["ctrl+nope"])).is_err());
+        assert_eq!(
+            parse_action("keydown", &args(&["LShift"])),
+            Ok(Action::KeyDown(KeybdKey::LShiftKey))
+        );
+        assert!(parse_action("keyup", &args(&[])).is_err());
+
+        assert_eq!(
+            parse_action("click", &args(&["left"])),
+            Ok(Action::Click(MouseButton::LeftButton, 1))
+        );
+        assert_eq!(
+            parse_action("click", &args(&["right", "2"])),
+            Ok(Action::Click(MouseButton::RightButton, 2))
+        );
+        assert_eq!(
+            parse_action("click", &args(&["x1"])),
+            Ok(Action::Click(MouseButton::X1Button, 1))
+        );
+        assert!(parse_action("click", &args(&["wheel"])).is_err());
+        assert!(parse_action("click", &args(&["left", "twice"])).is_err());
+
+        assert_eq!(
+            parse_action("move", &args(&["100", "-20"])),
+            Ok(Action::Move {
+                relative: false,
+                x: 100,
+                y: -20
+            })
+        );
+        assert_eq!(
+            parse_action("move", &args(&["--relative", "5", "5"])),
+            Ok(Action::Move {
+                relative: true,
+                x: 5,
+                y: 5
+            })
+        );
+        assert!(parse_action("move", &args(&["100"])).is_err());
+
+        assert_eq!(
+            parse_action("scroll", &args(&["-3"])),
+            Ok(Action::Scroll(-3))
+        );
+        assert!(parse_action("scroll", &args(&["3", "4"])).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn events_print_as_json() {
+        use inputbot::{KeyEvent, Modifiers};
+
+        let event = InputEvent {
+            input: Input::Keybd(KeybdKey::LShiftKey),
+            event: KeyEvent::Press,
+            is_injected: false,
+            modifiers: Modifiers::LSHIFT,
+            timestamp: UNIX_EPOCH + Duration::from_millis(1_697_712_345_500),
+            device: Some("AT keyboard".to_owned()),
+        };
+        let json: serde_json::Value = serde_json::from_str(&event_json(&event)).unwrap();
+        assert_eq!(json["key"], "LeftShift");
+        assert_eq!(json["modifiers"], serde_json::json!(["LSHIFT"]));
+        #[cfg(target_os = "linux")]
+        assert_eq!(json["evdev"], 42);
+        assert_eq!(serde_json::from_value::<InputEvent>(json).unwrap(), event);
+    }
+}
@@ -0,0 +1,50 @@
+use std::process::ExitCode;
+
+#[cfg(target_os = "linux")]
+const USAGE: &str = "\
+usage: inputbotd [--socket <path>] [--mode <octal>]
+
+Reads and sends input for other processes, which connect to the socket and send
+JSON requests, one per line. See inputbot::serve for the protocol.
+
+options:
+  --socket <path>   where to listen, by default $INPUTBOT_SOCKET or /run/inputbot.sock
+  --mode <octal>    permissions of the socket, by default 600; anyone who can connect
+                    to it can send input";
+
+#[cfg(target_os = "linux")]
+fn main() -> ExitCode {
+    let mut socket = inputbot::default_socket_path();
+    let mut mode = 0o600;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match (arg.as_str(), args.next()) {
+            ("--socket", Some(path)) => socket = path.into(),
+            ("--mode", Some(octal)) => match u32::from_str_radix(&octal, 8) {
+                Ok(bits) if bits <= 0o777 => mode = bits,
+                _ => {
+                    eprintln!("inputbotd: '{octal}' isn't a valid mode");
+                    return ExitCode::from(2);
+                }
+            },
+            _ => {
+                eprintln!("{USAGE}");
+                return ExitCode::from(2);
+            }
+        }
+    }
+
+    match inputbot::serve(&socket, mode) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("inputbotd: can't listen on {}: {err}", socket.display());
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn main() -> ExitCode {
+    eprintln!("inputbotd only runs on Linux, other platforms need no daemon to send input.");
+    ExitCode::FAILURE
+}
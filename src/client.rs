@@ -0,0 +1,261 @@
+//! Sends input and reads events through `inputbotd`, for programs without access to the
+//! input devices.
+//!
+//! After `connect`, the usual API goes through the daemon: `KeybdKey::press`,
+//! `MouseCursor::move_abs`, `KeybdKey::is_pressed` and the like become requests to it,
+//! and `handle_input_events` runs binds and the event listener on the events it reports.
+//!
+//! ```no_run
+//! use inputbot::{client, KeybdKey::*};
+//!
+//! client::connect_default().expect("is inputbotd running?");
+//! F1Key.bind(|| {
+//!     LShiftKey.press();
+//!     AKey.press();
+//!     AKey.release();
+//!     LShiftKey.release();
+//! });
+//! inputbot::handle_input_events(true);
+//! ```
+//!
+//! The daemon has already passed the events on when the client sees them, so they can't
+//! be blocked: block binds, remaps, layers and dual-role keys don't hide the keys they
+//! replace, and device binds don't run. Ask the daemon to block hotkeys with
+//! `DaemonRequest::Bind` instead.
+
+use crate::{common::*, daemon::*, public::*};
+use once_cell::sync::Lazy;
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    os::unix::net::UnixStream,
+    path::Path,
+    sync::{
+        atomic::AtomicU64,
+        mpsc::{self, Receiver, Sender},
+    },
+};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ClientError {
+    #[error("not connected to inputbotd")]
+    NotConnected,
+    #[error("lost the connection to inputbotd")]
+    Disconnected,
+    #[error("can't send the request to inputbotd")]
+    Io(#[from] io::Error),
+    #[error("inputbotd: {0}")]
+    Daemon(String),
+}
+
+type Reply = Result<Option<bool>, String>;
+
+struct Connection {
+    writer: Mutex<UnixStream>,
+    next_id: AtomicU64,
+    // Senders of the requests which wait for their reply, by id. None once the connection
+    // is closed.
+    pending: Mutex<Option<HashMap<u64, Sender<Reply>>>>,
+    events: Mutex<Option<Sender<InputEvent>>>,
+}
+
+static CONNECTION: Lazy<Mutex<Option<Arc<Connection>>>> = Lazy::new(|| Mutex::new(None));
+
+impl Connection {
+    // Starts reading the replies and events of the daemon on the other end of `stream`.
+    fn open(stream: UnixStream) -> io::Result<Arc<Connection>> {
+        let reader = BufReader::new(stream.try_clone()?);
+        let connection = Arc::new(Connection {
+            writer: Mutex::new(stream),
+            next_id: AtomicU64::new(0),
+            pending: Mutex::new(Some(HashMap::new())),
+            events: Mutex::new(None),
+        });
+
+        let reading = connection.clone();
+        spawn(move || {
+            for line in reader.lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                match serde_json::from_str::<DaemonMessage>(&line) {
+                    Ok(DaemonMessage::Reply {
+                        id: Some(id),
+                        error,
+                        value,
+                    }) => {
+                        let mut pending = reading.pending.lock().unwrap();
+                        if let Some(sender) =
+                            pending.as_mut().and_then(|pending| pending.remove(&id))
+                        {
+                            let _ = sender.send(error.map_or(Ok(value), Err));
+                        }
+                    }
+                    Ok(DaemonMessage::Event { event }) => {
+                        if let Some(sender) = &*reading.events.lock().unwrap() {
+                            let _ = sender.send(event);
+                        }
+                    }
+                    // Hotkeys bound with `DaemonRequest::Bind` arrive as events as well, which
+                    // run the binds of this process.
+                    _ => {}
+                }
+            }
+            // Dropping the senders wakes up everything waiting on the connection.
+            reading.pending.lock().unwrap().take();
+            reading.events.lock().unwrap().take();
+        });
+        Ok(connection)
+    }
+
+    fn request(&self, request: DaemonRequest) -> Result<Option<bool>, ClientError> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (sender, receiver) = mpsc::channel();
+        self.pending
+            .lock()
+            .unwrap()
+            .as_mut()
+            .ok_or(ClientError::Disconnected)?
+            .insert(id, sender);
+
+        let mut line = serde_json::to_string(&DaemonRequestLine {
+            id: Some(id),
+            request,
+        })
+        .unwrap();
+        line.push('\n');
+        if let Err(err) = self.writer.lock().unwrap().write_all(line.as_bytes()) {
+            if let Some(pending) = self.pending.lock().unwrap().as_mut() {
+                pending.remove(&id);
+            }
+            return Err(err.into());
+        }
+        match receiver.recv() {
+            Ok(reply) => reply.map_err(ClientError::Daemon),
+            Err(_) => Err(ClientError::Disconnected),
+        }
+    }
+}
+
+fn current() -> Result<Arc<Connection>, ClientError> {
+    CONNECTION
+        .lock()
+        .unwrap()
+        .clone()
+        .ok_or(ClientError::NotConnected)
+}
+
+/// Connects to the daemon listening on `path`, replacing any earlier connection. Input is
+/// sent through the daemon from now on.
+pub fn connect<P: AsRef<Path>>(path: P) -> io::Result<()> {
+    let connection = Connection::open(UnixStream::connect(path)?)?;
+    *CONNECTION.lock().unwrap() = Some(connection);
+    Ok(())
+}
+
+/// Connects to the daemon at `default_socket_path`.
+pub fn connect_default() -> io::Result<()> {
+    connect(default_socket_path())
+}
+
+/// Closes the connection, so that input is sent by this process again.
+pub fn disconnect() {
+    if let Some(connection) = CONNECTION.lock().unwrap().take() {
+        let _ = connection
+            .writer
+            .lock()
+            .unwrap()
+            .shutdown(std::net::Shutdown::Both);
+    }
+}
+
+/// Returns true if input goes through the daemon, see `connect`.
+pub fn is_connected() -> bool {
+    CONNECTION.lock().unwrap().is_some()
+}
+
+/// Sends a request to the daemon and waits for its reply, which holds the answer to
+/// `DaemonRequest::IsPressed` and `DaemonRequest::IsToggled`.
+///
+/// Most requests are sent by the usual API once connected. This is for the rest, like
+/// `DaemonRequest::Bind` with `block` to hide a hotkey from other programs.
+pub fn request(request: DaemonRequest) -> Result<Option<bool>, ClientError> {
+    current()?.request(request)
+}
+
+/// Sends `request` if connected, for the platform code which would otherwise send the
+/// input itself. Panics if the daemon can't be reached, like sending input fails.
+pub(crate) fn forward(request: DaemonRequest) -> Option<Option<bool>> {
+    if !is_connected() {
+        return None;
+    }
+    Some(self::request(request).unwrap_or_else(|err| panic!("{err}")))
+}
+
+/// Subscribes to the daemon's key and button events. The receiver is disconnected when
+/// the connection is lost.
+pub(crate) fn subscribe() -> Result<Receiver<InputEvent>, ClientError> {
+    let connection = current()?;
+    let (sender, receiver) = mpsc::channel();
+    *connection.events.lock().unwrap() = Some(sender);
+    connection.request(DaemonRequest::Subscribe)?;
+    Ok(receiver)
+}
+
+pub(crate) fn unsubscribe() {
+    if let Ok(connection) = current() {
+        connection.events.lock().unwrap().take();
+        let _ = connection.request(DaemonRequest::Unsubscribe);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hotkey::Hotkey;
+
+    #[test]
+    fn requests_are_answered() -> Result<(), Box<dyn std::error::Error>> {
+        let _lock = lock_global_state();
+        assert!(matches!(
+            request(DaemonRequest::Subscribe),
+            Err(ClientError::NotConnected)
+        ));
+
+        let (client, server) = UnixStream::pair()?;
+        spawn(move || handle_connection(server));
+        let connection = Connection::open(client)?;
+
+        let key = KeybdKey::OtherKey(0xFFFF_0402);
+        let hotkey = Hotkey {
+            modifiers: Modifiers::LCONTROL,
+            input: Input::Keybd(key),
+        };
+        assert_eq!(
+            connection.request(DaemonRequest::Bind {
+                hotkey,
+                block: false
+            })?,
+            None
+        );
+        assert!(key.is_bound());
+        connection.request(DaemonRequest::Unbind { hotkey })?;
+        assert!(!key.is_bound());
+        assert!(matches!(
+            connection.request(DaemonRequest::Unbind { hotkey }),
+            Err(ClientError::Daemon(_))
+        ));
+
+        // The daemon goes away once it has read everything.
+        connection
+            .writer
+            .lock()
+            .unwrap()
+            .shutdown(std::net::Shutdown::Write)?;
+        assert!(matches!(
+            connection.request(DaemonRequest::Subscribe),
+            Err(ClientError::Io(_) | ClientError::Disconnected)
+        ));
+        Ok(())
+    }
+}
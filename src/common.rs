@@ -1,39 +1,929 @@
-use crate::public::*;
-use once_cell::sync::Lazy;
+#[cfg(target_os = "linux")]
+use crate::linux::DeviceFilter;
+use crate::{
+    layers::{process_layers, LAYERS},
+    public::*,
+};
+use once_cell::{sync::Lazy, unsync};
 pub use std::{
-    collections::hash_map::HashMap,
+    collections::{hash_map::HashMap, HashSet},
+    hash::Hash,
     sync::atomic::{AtomicPtr, Ordering},
-    sync::{atomic::AtomicBool, Arc, Mutex},
-    thread::spawn,
+    sync::{
+        atomic::AtomicBool,
+        mpsc::{channel, Sender},
+        Arc, Mutex,
+    },
+    thread::{sleep, spawn},
+    time::{Instant, SystemTime},
 };
 
+#[derive(Clone)]
 pub enum Bind {
-    Normal(Handler),
+    Normal(EventHandler),
     #[cfg(target_os = "windows")]
-    Release(Handler),
-    Block(BlockHandler),
+    Release(EventHandler),
+    Block(EventHandler),
     Blockable(BlockableHandler),
 }
 
 pub type Handler = Arc<dyn Fn() + Send + Sync + 'static>;
-pub type BlockHandler = Arc<dyn Fn() + Send + Sync + 'static>;
-pub type BlockableHandler = Arc<dyn Fn() -> BlockInput + Send + Sync + 'static>;
+pub type EventHandler = Arc<dyn Fn(&InputEvent) + Send + Sync + 'static>;
+pub type BlockableHandler = Arc<dyn Fn(&InputEvent) -> BlockInput + Send + Sync + 'static>;
+/// An event which is only built once something needs it, since reading the modifiers can
+/// be slow.
+pub type LazyInputEvent<'a> = unsync::Lazy<InputEvent, Box<dyn FnOnce() -> InputEvent + 'a>>;
 pub type KeybdBindMap = HashMap<KeybdKey, Bind>;
 pub type MouseBindMap = HashMap<MouseButton, Bind>;
+pub type KeybdBindOptionsMap = HashMap<KeybdKey, BindOptions>;
+pub type MouseBindOptionsMap = HashMap<MouseButton, BindOptions>;
+pub type EventListener = Arc<dyn Fn(&InputEvent) + Send + Sync + 'static>;
+#[cfg(target_os = "linux")]
+pub type KeybdDeviceBindMap = HashMap<(DeviceFilter, KeybdKey), Bind>;
+pub type GestureBindMap<K> = HashMap<(K, Gesture), Handler>;
 
 pub static HANDLE_EVENTS: AtomicBool = AtomicBool::new(false);
 pub static KEYBD_BINDS: Lazy<Mutex<KeybdBindMap>> = Lazy::new(|| Mutex::new(KeybdBindMap::new()));
 pub static KEYBD_RELEASE_BINDS: Lazy<Mutex<KeybdBindMap>> =
     Lazy::new(|| Mutex::new(KeybdBindMap::new()));
+pub static KEYBD_BIND_OPTIONS: Lazy<Mutex<KeybdBindOptionsMap>> =
+    Lazy::new(|| Mutex::new(KeybdBindOptionsMap::new()));
+#[cfg(target_os = "linux")]
+pub static KEYBD_DEVICE_BINDS: Lazy<Mutex<KeybdDeviceBindMap>> =
+    Lazy::new(|| Mutex::new(KeybdDeviceBindMap::new()));
 pub static MOUSE_BINDS: Lazy<Mutex<MouseBindMap>> = Lazy::new(|| Mutex::new(MouseBindMap::new()));
 pub static MOUSE_RELEASE_BINDS: Lazy<Mutex<MouseBindMap>> =
     Lazy::new(|| Mutex::new(MouseBindMap::new()));
+pub static MOUSE_BIND_OPTIONS: Lazy<Mutex<MouseBindOptionsMap>> =
+    Lazy::new(|| Mutex::new(MouseBindOptionsMap::new()));
+pub static KEYBD_GESTURE_BINDS: Lazy<Mutex<GestureBindMap<KeybdKey>>> =
+    Lazy::new(|| Mutex::new(GestureBindMap::new()));
+pub static MOUSE_GESTURE_BINDS: Lazy<Mutex<GestureBindMap<MouseButton>>> =
+    Lazy::new(|| Mutex::new(GestureBindMap::new()));
+pub static GESTURE_TIMING: Lazy<Mutex<GestureTiming>> =
+    Lazy::new(|| Mutex::new(GestureTiming::default()));
+pub static REMAPS: Lazy<Mutex<HashMap<KeybdKey, KeybdKey>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+pub static DISPLAY_NAMES: Lazy<Mutex<HashMap<Input, String>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+pub static DUAL_ROLES: Lazy<Mutex<HashMap<KeybdKey, DualRole>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+pub static DUAL_ROLE_OPTIONS: Lazy<Mutex<DualRoleOptions>> =
+    Lazy::new(|| Mutex::new(DualRoleOptions::default()));
+pub static EVENT_LISTENER: Lazy<Mutex<Option<EventListener>>> = Lazy::new(|| Mutex::new(None));
+
+type Injection = Box<dyn FnOnce() + Send + 'static>;
+
+static INJECTIONS: Lazy<Mutex<Sender<Injection>>> = Lazy::new(|| {
+    let (sender, receiver) = channel::<Injection>();
+    spawn(move || {
+        let _release_grabs = ReleaseGrabs;
+        for injection in receiver {
+            injection();
+        }
+    });
+    Mutex::new(sender)
+});
+
+// Gives grabbed keyboards back to the system when the injection thread exits, e.g. because
+// the fake device can't be created. Their events couldn't be passed on anymore otherwise.
+struct ReleaseGrabs;
+
+impl Drop for ReleaseGrabs {
+    fn drop(&mut self) {
+        #[cfg(target_os = "linux")]
+        crate::linux::release_grabs();
+    }
+}
+
+// Events for the listener are delivered from a thread of their own, so a slow listener
+// doesn't hold up the hooks, and in order.
+static LISTENER_EVENTS: Lazy<Mutex<Sender<InputEvent>>> = Lazy::new(|| {
+    let (sender, receiver) = channel::<InputEvent>();
+    spawn(move || {
+        for event in receiver {
+            let listener = EVENT_LISTENER.lock().unwrap().clone();
+            if let Some(listener) = listener {
+                listener(&event);
+            }
+        }
+    });
+    Mutex::new(sender)
+});
+
+// Keys whose last press was blocked, so that repeats which don't fire the bind
+// are blocked consistently with the press.
+static KEYBD_BLOCKED: Lazy<Mutex<HashSet<KeybdKey>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+#[derive(Default)]
+struct GestureState {
+    // Counts presses, so that timers can tell whether the press they were started for
+    // is still the latest one.
+    presses: u64,
+    pressed_at: Option<Instant>,
+    hold_fired: bool,
+    // The press of a tap which is waiting to see whether a second tap follows.
+    pending_tap: Option<u64>,
+}
+
+impl GestureState {
+    // Returns the new press, which the hold timer is started for.
+    fn press(&mut self, now: Instant) -> u64 {
+        self.presses += 1;
+        self.pressed_at = Some(now);
+        self.hold_fired = false;
+        self.presses
+    }
+
+    // Called when the hold timer of `press` runs out. Returns true if the key is held.
+    fn hold_elapsed(&mut self, press: u64) -> bool {
+        if self.presses != press || self.pressed_at.is_none() {
+            return false;
+        }
+        self.hold_fired = true;
+        self.pending_tap = None;
+        true
+    }
+
+    // `wait_for_double_tap` holds a tap back until it's clear whether a second one follows.
+    fn release(
+        &mut self,
+        now: Instant,
+        timing: GestureTiming,
+        wait_for_double_tap: bool,
+    ) -> GestureRelease {
+        let pressed_at = self.pressed_at.take();
+        let is_tap = !self.hold_fired
+            && pressed_at.is_some_and(|pressed_at| now.duration_since(pressed_at) < timing.hold);
+        if !is_tap {
+            self.pending_tap = None;
+            GestureRelease::Nothing
+        } else if !wait_for_double_tap {
+            GestureRelease::Tap
+        } else if self.pending_tap.take().is_some() {
+            GestureRelease::DoubleTap
+        } else {
+            self.pending_tap = Some(self.presses);
+            GestureRelease::PendingTap(self.presses)
+        }
+    }
+
+    // Called when the double tap timer of `press` runs out. Returns true if the tap stands
+    // on its own.
+    fn double_tap_elapsed(&mut self, press: u64) -> bool {
+        // A newer press means a second tap (or a hold) is under way.
+        if self.pending_tap != Some(press) || self.presses != press {
+            return false;
+        }
+        self.pending_tap = None;
+        true
+    }
+}
+
+// What a release makes of the press before it.
+#[derive(Debug, Eq, PartialEq)]
+enum GestureRelease {
+    Nothing,
+    Tap,
+    DoubleTap,
+    // A tap of this press, which waits for the double tap timer.
+    PendingTap(u64),
+}
+
+type GestureStatesMap<K> = HashMap<K, GestureState>;
+
+static KEYBD_GESTURE_STATES: Lazy<Mutex<GestureStatesMap<KeybdKey>>> =
+    Lazy::new(|| Mutex::new(GestureStatesMap::new()));
+static MOUSE_GESTURE_STATES: Lazy<Mutex<GestureStatesMap<MouseButton>>> =
+    Lazy::new(|| Mutex::new(GestureStatesMap::new()));
+
+#[derive(Copy, Clone)]
+pub struct DualRole {
+    pub tap: KeybdKey,
+    pub hold: KeybdKey,
+}
+
+struct ActiveDualRole {
+    role: DualRole,
+    press: u64,
+    pressed_at: Instant,
+    held: bool,
+}
+
+#[derive(Default)]
+struct DualRoleState {
+    presses: u64,
+    active: HashMap<KeybdKey, ActiveDualRole>,
+    // The dual-role key which is down but hasn't been decided to be a tap or a hold yet.
+    // Only one key can be undecided at a time.
+    undecided: Option<KeybdKey>,
+    // Events of other keys, held back until the undecided key is decided.
+    buffered: Vec<(KeybdKey, KeyEvent)>,
+    // Events to inject in place of the consumed ones.
+    output: Vec<(KeybdKey, KeyEvent)>,
+}
+
+impl DualRoleState {
+    /// Turns the event into events of the tap or hold key in `output`, and returns true if
+    /// it was consumed, and the press to start the tapping term timer for. `role` is the
+    /// dual role of `key`, if it has one.
+    fn handle(
+        &mut self,
+        role: Option<DualRole>,
+        key: KeybdKey,
+        event: KeyEvent,
+        options: DualRoleOptions,
+        now: Instant,
+    ) -> (bool, Option<u64>) {
+        if let Some(role) = role {
+            match event {
+                KeyEvent::Press => {
+                    if self.active.contains_key(&key) {
+                        return (true, None);
+                    }
+                    // Pressing a second dual-role key makes the first one a hold, so that
+                    // e.g. two modifiers on home row keys can be chorded.
+                    if let Some(undecided) = self.undecided {
+                        self.hold(undecided);
+                    }
+
+                    self.presses += 1;
+                    let press = self.presses;
+                    self.active.insert(
+                        key,
+                        ActiveDualRole {
+                            role,
+                            press,
+                            pressed_at: now,
+                            held: false,
+                        },
+                    );
+                    self.undecided = Some(key);
+                    return (true, Some(press));
+                }
+                KeyEvent::Repeat => return (self.active.contains_key(&key), None),
+                KeyEvent::Release => {
+                    let Some(active) = self.active.get(&key) else {
+                        return (false, None);
+                    };
+                    if !active.held && now.duration_since(active.pressed_at) >= options.tapping_term
+                    {
+                        self.hold(key);
+                    }
+
+                    let active = self.active.remove(&key).unwrap();
+                    if active.held {
+                        self.output.push((active.role.hold, KeyEvent::Release));
+                    } else {
+                        self.tap(active.role);
+                    }
+                    return (true, None);
+                }
+            }
+        }
+
+        let Some(undecided) = self.undecided else {
+            return (false, None);
+        };
+        self.buffered.push((key, event));
+        let hold = match options.mode {
+            DualRoleMode::Timeout => false,
+            DualRoleMode::PermissiveHold => {
+                event == KeyEvent::Release && self.buffered.contains(&(key, KeyEvent::Press))
+            }
+            DualRoleMode::HoldOnOtherKeyPress => event == KeyEvent::Press,
+        };
+        if hold {
+            self.hold(undecided);
+        }
+        (true, None)
+    }
+
+    // Called when the tapping term of `press` runs out.
+    fn tapping_term_elapsed(&mut self, key: KeybdKey, press: u64) {
+        if self.undecided == Some(key)
+            && self
+                .active
+                .get(&key)
+                .is_some_and(|active| active.press == press)
+        {
+            self.hold(key);
+        }
+    }
+
+    fn hold(&mut self, key: KeybdKey) {
+        if let Some(active) = self.active.get_mut(&key) {
+            active.held = true;
+            self.output.push((active.role.hold, KeyEvent::Press));
+        }
+        self.decided();
+    }
+
+    fn tap(&mut self, role: DualRole) {
+        self.output.push((role.tap, KeyEvent::Press));
+        self.output.push((role.tap, KeyEvent::Release));
+        self.decided();
+    }
+
+    fn decided(&mut self) {
+        self.undecided = None;
+        self.output.append(&mut self.buffered);
+    }
+
+    fn inject_output(&mut self) {
+        for (key, event) in self.output.drain(..) {
+            inject_keybd_event(key, event);
+        }
+    }
+}
+
+static DUAL_ROLE_STATE: Lazy<Mutex<DualRoleState>> =
+    Lazy::new(|| Mutex::new(DualRoleState::default()));
+// Remapped keys which are down, and the key which was pressed in their place. The release
+// goes to the same key even if the remap changed in the meantime.
+static REMAPPED_DOWN: Lazy<Mutex<HashMap<KeybdKey, KeybdKey>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Sends input from a dedicated thread, in the order it was queued.
+///
+/// Windows doesn't allow sending input from the hook thread, and injected events have
+/// to arrive in order, e.g. a modifier has to go down before the key it modifies.
+pub fn queue_injection<F: FnOnce() + Send + 'static>(injection: F) {
+    // If the injection thread is gone, so is the input, like it is when sending fails.
+    let _ = INJECTIONS.lock().unwrap().send(Box::new(injection));
+}
+
+pub fn inject_keybd_event(key: KeybdKey, event: KeyEvent) {
+    queue_injection(move || match event {
+        KeyEvent::Press | KeyEvent::Repeat => key.press(),
+        KeyEvent::Release => key.release(),
+    });
+}
+
+/// Returns true if anything needs to see keyboard events.
+pub fn keybd_bound() -> bool {
+    !KEYBD_BINDS.lock().unwrap().is_empty()
+        || !LAYERS.lock().unwrap().is_empty()
+        || !REMAPS.lock().unwrap().is_empty()
+        || !DUAL_ROLES.lock().unwrap().is_empty()
+        || !KEYBD_RELEASE_BINDS.lock().unwrap().is_empty()
+        || !KEYBD_GESTURE_BINDS.lock().unwrap().is_empty()
+        || EVENT_LISTENER.lock().unwrap().is_some()
+        || keybd_device_bound()
+}
+
+#[cfg(target_os = "linux")]
+fn keybd_device_bound() -> bool {
+    !KEYBD_DEVICE_BINDS.lock().unwrap().is_empty()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn keybd_device_bound() -> bool {
+    false
+}
+
+/// Returns true if anything needs to see mouse button events.
+pub fn mouse_bound() -> bool {
+    !MOUSE_BINDS.lock().unwrap().is_empty()
+        || !MOUSE_RELEASE_BINDS.lock().unwrap().is_empty()
+        || !MOUSE_GESTURE_BINDS.lock().unwrap().is_empty()
+        || EVENT_LISTENER.lock().unwrap().is_some()
+}
 
 pub fn should_continue(auto_stop: bool) -> bool {
-    HANDLE_EVENTS.load(Ordering::Relaxed)
-        && (!auto_stop
-            || !MOUSE_BINDS.lock().unwrap().is_empty()
-            || !KEYBD_BINDS.lock().unwrap().is_empty()
-            || !KEYBD_RELEASE_BINDS.lock().unwrap().is_empty()
-            || !MOUSE_RELEASE_BINDS.lock().unwrap().is_empty())
+    HANDLE_EVENTS.load(Ordering::Relaxed) && (!auto_stop || keybd_bound() || mouse_bound())
+}
+
+/// Runs everything bound to `key` and returns whether the platform should hide the
+/// event from the rest of the system.
+///
+/// `injected` is true for events sent by inputbot itself. These are never remapped
+/// again, which would loop. `device` is the name of the device the event came from, if
+/// the platform knows it.
+pub fn dispatch_keybd_event(
+    key: KeybdKey,
+    event: KeyEvent,
+    injected: bool,
+    device: Option<&str>,
+) -> BlockInput {
+    dispatch_keybd(
+        key,
+        event,
+        injected,
+        lazy_input_event(Input::Keybd(key), event, injected, device),
+    )
+}
+
+/// Runs everything bound to `button` and returns whether the platform should hide the
+/// event from the rest of the system.
+pub fn dispatch_mouse_event(
+    button: MouseButton,
+    event: KeyEvent,
+    injected: bool,
+    device: Option<&str>,
+) -> BlockInput {
+    dispatch_mouse(
+        button,
+        event,
+        injected,
+        lazy_input_event(Input::Mouse(button), event, injected, device),
+    )
+}
+
+/// Like `dispatch_keybd_event` and `dispatch_mouse_event`, for an event which already has
+/// its modifiers and timestamp, like one received from the daemon.
+#[cfg(any(test, all(feature = "daemon", target_os = "linux")))]
+pub fn dispatch_input_event(input_event: InputEvent) -> BlockInput {
+    let InputEvent {
+        input,
+        event,
+        is_injected,
+        ..
+    } = input_event;
+    let input_event: LazyInputEvent = unsync::Lazy::new(Box::new(move || input_event));
+    match input {
+        Input::Keybd(key) => dispatch_keybd(key, event, is_injected, input_event),
+        Input::Mouse(button) => dispatch_mouse(button, event, is_injected, input_event),
+    }
+}
+
+fn dispatch_keybd(
+    key: KeybdKey,
+    event: KeyEvent,
+    injected: bool,
+    input_event: LazyInputEvent,
+) -> BlockInput {
+    notify_listener(&input_event);
+    if injected && key.bind_options().ignore_injected {
+        return BlockInput::DontBlock;
+    }
+    if !injected
+        && (process_layers(key, event)
+            || process_remap(key, event)
+            || process_dual_role(key, event))
+    {
+        return BlockInput::Block;
+    }
+
+    let gesture = recognize_gesture(
+        &KEYBD_GESTURE_BINDS,
+        &KEYBD_GESTURE_STATES,
+        key,
+        event,
+        Instant::now(),
+    );
+    let bind = run_keybd_bind(key, event, &input_event);
+    if gesture == BlockInput::Block || bind == BlockInput::Block {
+        BlockInput::Block
+    } else {
+        BlockInput::DontBlock
+    }
+}
+
+fn dispatch_mouse(
+    button: MouseButton,
+    event: KeyEvent,
+    injected: bool,
+    input_event: LazyInputEvent,
+) -> BlockInput {
+    notify_listener(&input_event);
+    if injected && button.bind_options().ignore_injected {
+        return BlockInput::DontBlock;
+    }
+    let gesture = recognize_gesture(
+        &MOUSE_GESTURE_BINDS,
+        &MOUSE_GESTURE_STATES,
+        button,
+        event,
+        Instant::now(),
+    );
+    let bind = run_mouse_bind(button, event, &input_event);
+    if gesture == BlockInput::Block || bind == BlockInput::Block {
+        BlockInput::Block
+    } else {
+        BlockInput::DontBlock
+    }
+}
+
+pub fn lazy_input_event(
+    input: Input,
+    event: KeyEvent,
+    injected: bool,
+    device: Option<&str>,
+) -> LazyInputEvent<'_> {
+    // Taken now, so that it's when the event arrived rather than when it was first needed.
+    let timestamp = SystemTime::now();
+    unsync::Lazy::new(Box::new(move || {
+        // The platform may not count the event's own key as held yet.
+        let mut modifiers = Modifiers::current();
+        if let Input::Keybd(key) = input {
+            modifiers.set(Modifiers::of_key(key), event != KeyEvent::Release);
+        }
+        InputEvent {
+            input,
+            event,
+            is_injected: injected,
+            modifiers,
+            timestamp,
+            device: device.map(str::to_owned),
+        }
+    }))
+}
+
+pub fn notify_listener(event: &LazyInputEvent) {
+    if EVENT_LISTENER.lock().unwrap().is_some() {
+        let _ = LISTENER_EVENTS.lock().unwrap().send((**event).clone());
+    }
+}
+
+/// Runs `cb` with `event` on a thread of its own.
+pub fn spawn_handler(cb: EventHandler, event: &InputEvent) {
+    let event = event.clone();
+    spawn(move || cb(&event));
+}
+
+/// Replaces the events of remapped keys with events of the key they are mapped to, and
+/// returns true if the event was consumed.
+fn process_remap(key: KeybdKey, event: KeyEvent) -> bool {
+    let mut remapped_down = REMAPPED_DOWN.lock().unwrap();
+    let mapped = match event {
+        KeyEvent::Press => match REMAPS.lock().unwrap().get(&key) {
+            Some(&mapped) => *remapped_down.entry(key).or_insert(mapped),
+            None => return false,
+        },
+        KeyEvent::Repeat => match remapped_down.get(&key) {
+            Some(&mapped) => mapped,
+            None => return false,
+        },
+        KeyEvent::Release => match remapped_down.remove(&key) {
+            Some(mapped) => mapped,
+            None => return false,
+        },
+    };
+    inject_keybd_event(mapped, event);
+    true
+}
+
+/// Turns the events of dual-role keys into the events of their tap or hold key, and
+/// returns true if the event was consumed.
+fn process_dual_role(key: KeybdKey, event: KeyEvent) -> bool {
+    let role = DUAL_ROLES.lock().unwrap().get(&key).copied();
+    let options = *DUAL_ROLE_OPTIONS.lock().unwrap();
+    let mut state = DUAL_ROLE_STATE.lock().unwrap();
+
+    let (consumed, press) = state.handle(role, key, event, options, Instant::now());
+    state.inject_output();
+    if let Some(press) = press {
+        spawn(move || {
+            sleep(options.tapping_term);
+            let mut state = DUAL_ROLE_STATE.lock().unwrap();
+            state.tapping_term_elapsed(key, press);
+            state.inject_output();
+        });
+    }
+    consumed
+}
+
+fn run_keybd_bind(key: KeybdKey, event: KeyEvent, input_event: &LazyInputEvent) -> BlockInput {
+    match event {
+        KeyEvent::Press | KeyEvent::Repeat => {
+            let fire = event == KeyEvent::Press || key.bind_options().repeat;
+            let bind = KEYBD_BINDS.lock().unwrap().get(&key).cloned();
+            let block = match bind {
+                Some(Bind::Normal(cb)) => {
+                    if fire {
+                        spawn_handler(cb, input_event);
+                    }
+                    BlockInput::DontBlock
+                }
+                Some(Bind::Block(cb)) => {
+                    if fire {
+                        spawn_handler(cb, input_event);
+                    }
+                    BlockInput::Block
+                }
+                Some(Bind::Blockable(cb)) => {
+                    if fire {
+                        cb(input_event)
+                    } else if KEYBD_BLOCKED.lock().unwrap().contains(&key) {
+                        BlockInput::Block
+                    } else {
+                        BlockInput::DontBlock
+                    }
+                }
+                _ => BlockInput::DontBlock,
+            };
+            if event == KeyEvent::Press {
+                let mut blocked = KEYBD_BLOCKED.lock().unwrap();
+                if block == BlockInput::Block {
+                    blocked.insert(key);
+                } else {
+                    blocked.remove(&key);
+                }
+            }
+            block
+        }
+        KeyEvent::Release => {
+            KEYBD_BLOCKED.lock().unwrap().remove(&key);
+            #[cfg(target_os = "windows")]
+            if let Some(Bind::Release(cb)) = KEYBD_RELEASE_BINDS.lock().unwrap().get(&key) {
+                spawn_handler(Arc::clone(cb), input_event);
+            }
+            BlockInput::DontBlock
+        }
+    }
+}
+
+fn run_mouse_bind(
+    button: MouseButton,
+    event: KeyEvent,
+    input_event: &LazyInputEvent,
+) -> BlockInput {
+    match event {
+        KeyEvent::Press | KeyEvent::Repeat => {
+            let bind = MOUSE_BINDS.lock().unwrap().get(&button).cloned();
+            match bind {
+                Some(Bind::Normal(cb)) => {
+                    spawn_handler(cb, input_event);
+                    BlockInput::DontBlock
+                }
+                Some(Bind::Block(cb)) => {
+                    spawn_handler(cb, input_event);
+                    BlockInput::Block
+                }
+                Some(Bind::Blockable(cb)) => cb(input_event),
+                _ => BlockInput::DontBlock,
+            }
+        }
+        KeyEvent::Release => {
+            #[cfg(target_os = "windows")]
+            if let Some(Bind::Release(cb)) = MOUSE_RELEASE_BINDS.lock().unwrap().get(&button) {
+                spawn_handler(Arc::clone(cb), input_event);
+            }
+            BlockInput::DontBlock
+        }
+    }
+}
+
+/// Tells taps, double taps and holds apart from the presses and releases of `key`.
+///
+/// Inputs with gesture binds are blocked entirely, so that e.g. holding a key doesn't
+/// also type it. `now` is when the event arrived.
+fn recognize_gesture<K>(
+    binds: &'static Mutex<GestureBindMap<K>>,
+    states: &'static Mutex<GestureStatesMap<K>>,
+    key: K,
+    event: KeyEvent,
+    now: Instant,
+) -> BlockInput
+where
+    K: Copy + Eq + Hash + Send + 'static,
+{
+    let (tap, double_tap, hold) = {
+        let binds = binds.lock().unwrap();
+        (
+            binds.get(&(key, Gesture::Tap)).cloned(),
+            binds.get(&(key, Gesture::DoubleTap)).cloned(),
+            binds.get(&(key, Gesture::Hold)).cloned(),
+        )
+    };
+    if tap.is_none() && double_tap.is_none() && hold.is_none() {
+        return BlockInput::DontBlock;
+    }
+
+    let timing = *GESTURE_TIMING.lock().unwrap();
+    let mut states_guard = states.lock().unwrap();
+    let state = states_guard.entry(key).or_default();
+    match event {
+        KeyEvent::Press => {
+            let press = state.press(now);
+            if let Some(hold) = hold {
+                spawn(move || {
+                    sleep(timing.hold);
+                    let held = states
+                        .lock()
+                        .unwrap()
+                        .entry(key)
+                        .or_default()
+                        .hold_elapsed(press);
+                    if held {
+                        hold();
+                    }
+                });
+            }
+        }
+        KeyEvent::Repeat => {}
+        KeyEvent::Release => match state.release(now, timing, double_tap.is_some()) {
+            GestureRelease::Nothing => {}
+            GestureRelease::Tap => {
+                if let Some(tap) = tap {
+                    spawn(move || tap());
+                }
+            }
+            GestureRelease::DoubleTap => {
+                if let Some(double_tap) = double_tap {
+                    spawn(move || double_tap());
+                }
+            }
+            GestureRelease::PendingTap(press) => {
+                spawn(move || {
+                    sleep(timing.double_tap);
+                    let single = states
+                        .lock()
+                        .unwrap()
+                        .entry(key)
+                        .or_default()
+                        .double_tap_elapsed(press);
+                    if single {
+                        if let Some(tap) = tap {
+                            tap();
+                        }
+                    }
+                });
+            }
+        },
+    }
+    BlockInput::Block
+}
+
+/// Serializes the tests which change the global binds, layers, config and display names,
+/// so that they don't see each other's.
+#[cfg(test)]
+pub(crate) fn lock_global_state() -> std::sync::MutexGuard<'static, ()> {
+    static GLOBAL_STATE: Mutex<()> = Mutex::new(());
+    GLOBAL_STATE.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use KeybdKey::*;
+
+    #[test]
+    fn gestures_are_recognized() {
+        let timing = GestureTiming {
+            hold: Duration::from_millis(100),
+            double_tap: Duration::from_millis(100),
+        };
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let mut state = GestureState::default();
+
+        // A tap on its own, once the double tap timer runs out.
+        let press = state.press(at(0));
+        assert_eq!(
+            state.release(at(10), timing, true),
+            GestureRelease::PendingTap(press)
+        );
+        assert!(!state.hold_elapsed(press));
+        assert!(state.double_tap_elapsed(press));
+
+        // A second tap before the timer runs out.
+        let first = state.press(at(200));
+        assert_eq!(
+            state.release(at(210), timing, true),
+            GestureRelease::PendingTap(first)
+        );
+        state.press(at(230));
+        assert_eq!(
+            state.release(at(240), timing, true),
+            GestureRelease::DoubleTap
+        );
+        assert!(!state.double_tap_elapsed(first));
+
+        // Without a double tap bind, taps don't wait.
+        state.press(at(400));
+        assert_eq!(state.release(at(410), timing, false), GestureRelease::Tap);
+
+        // A hold isn't a tap as well.
+        let press = state.press(at(600));
+        assert!(state.hold_elapsed(press));
+        assert_eq!(
+            state.release(at(800), timing, true),
+            GestureRelease::Nothing
+        );
+
+        // Neither is a long press without a hold bind.
+        state.press(at(1000));
+        assert_eq!(
+            state.release(at(1200), timing, true),
+            GestureRelease::Nothing
+        );
+    }
+
+    #[test]
+    fn dual_roles_resolve() {
+        let role = DualRole {
+            tap: EscapeKey,
+            hold: LControlKey,
+        };
+        let mut options = DualRoleOptions {
+            tapping_term: Duration::from_millis(200),
+            mode: DualRoleMode::Timeout,
+        };
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let mut state = DualRoleState::default();
+        let output = |state: &mut DualRoleState| state.output.drain(..).collect::<Vec<_>>();
+
+        // A tap, with the key of another event replayed after it.
+        let (consumed, press) =
+            state.handle(Some(role), CapsLockKey, KeyEvent::Press, options, at(0));
+        assert!(consumed && press.is_some());
+        assert!(state.handle(None, AKey, KeyEvent::Press, options, at(10)).0);
+        state.handle(Some(role), CapsLockKey, KeyEvent::Release, options, at(50));
+        assert_eq!(
+            output(&mut state),
+            [
+                (EscapeKey, KeyEvent::Press),
+                (EscapeKey, KeyEvent::Release),
+                (AKey, KeyEvent::Press)
+            ]
+        );
+        assert!(
+            !state
+                .handle(None, AKey, KeyEvent::Release, options, at(60))
+                .0
+        );
+
+        // A hold, once the tapping term runs out.
+        let (_, press) = state.handle(Some(role), CapsLockKey, KeyEvent::Press, options, at(100));
+        state.tapping_term_elapsed(CapsLockKey, press.unwrap());
+        state.handle(Some(role), CapsLockKey, KeyEvent::Release, options, at(400));
+        assert_eq!(
+            output(&mut state),
+            [
+                (LControlKey, KeyEvent::Press),
+                (LControlKey, KeyEvent::Release)
+            ]
+        );
+
+        // A release after the tapping term is a hold even if the timer hasn't run yet.
+        state.handle(Some(role), CapsLockKey, KeyEvent::Press, options, at(500));
+        state.handle(Some(role), CapsLockKey, KeyEvent::Release, options, at(800));
+        assert_eq!(
+            output(&mut state),
+            [
+                (LControlKey, KeyEvent::Press),
+                (LControlKey, KeyEvent::Release)
+            ]
+        );
+
+        // Another key pressed and released while the key is down makes it a hold.
+        options.mode = DualRoleMode::PermissiveHold;
+        state.handle(Some(role), CapsLockKey, KeyEvent::Press, options, at(1000));
+        state.handle(None, AKey, KeyEvent::Press, options, at(1010));
+        assert_eq!(output(&mut state), []);
+        state.handle(None, AKey, KeyEvent::Release, options, at(1020));
+        state.handle(
+            Some(role),
+            CapsLockKey,
+            KeyEvent::Release,
+            options,
+            at(1030),
+        );
+        assert_eq!(
+            output(&mut state),
+            [
+                (LControlKey, KeyEvent::Press),
+                (AKey, KeyEvent::Press),
+                (AKey, KeyEvent::Release),
+                (LControlKey, KeyEvent::Release)
+            ]
+        );
+
+        // Pressing another key is enough.
+        options.mode = DualRoleMode::HoldOnOtherKeyPress;
+        state.handle(Some(role), CapsLockKey, KeyEvent::Press, options, at(1100));
+        state.handle(None, AKey, KeyEvent::Press, options, at(1110));
+        assert_eq!(
+            output(&mut state),
+            [(LControlKey, KeyEvent::Press), (AKey, KeyEvent::Press)]
+        );
+    }
+
+    #[test]
+    fn prebuilt_events_are_dispatched_as_is() {
+        let _lock = lock_global_state();
+        let key = OtherKey(0xFFFF_0403);
+        let (sender, receiver) = channel();
+        let sender = Mutex::new(sender);
+        key.bind_event(move |event| {
+            let _ = sender.lock().unwrap().send(event.clone());
+        });
+
+        let event = InputEvent {
+            input: Input::Keybd(key),
+            event: KeyEvent::Press,
+            is_injected: false,
+            modifiers: Modifiers::LSHIFT | Modifiers::CAPS_LOCK,
+            timestamp: SystemTime::UNIX_EPOCH,
+            device: Some("keyboard".to_owned()),
+        };
+        dispatch_input_event(event.clone());
+        let received = receiver.recv_timeout(Duration::from_secs(1));
+        key.unbind();
+        assert_eq!(received, Ok(event));
+    }
 }
@@ -0,0 +1,319 @@
+use crate::{common::*, hotkey::*, public::*};
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+use thiserror::Error;
+
+/// Binds, remaps and macros read from a TOML file, see `load_config`.
+///
+/// ```toml
+/// [binds]
+/// "Ctrl+Shift+F1" = { type = "text", value = "Hello, world!" }
+/// "F2" = { type = "macro", value = "greet", block = true }
+///
+/// [remaps]
+/// CapsLock = "Escape"
+///
+/// [macros]
+/// greet = [
+///     { type = "text", value = "Hi" },
+///     { type = "sleep", value = 100 },
+///     { type = "key", value = "Enter" },
+/// ]
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(default)]
+    pub binds: HashMap<Hotkey, ConfigBind>,
+    #[serde(default)]
+    pub remaps: HashMap<KeybdKey, KeybdKey>,
+    #[serde(default)]
+    pub macros: HashMap<String, Vec<ConfigAction>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ConfigBind {
+    #[serde(flatten)]
+    pub action: ConfigAction,
+    /// Hide the hotkey's key from the rest of the system when the bind fires.
+    #[serde(default)]
+    pub block: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "lowercase")]
+pub enum ConfigAction {
+    /// Type the text with `KeySequence`.
+    Text(String),
+    /// Send hotkeys like `"Ctrl+C"` or `"Ctrl+X Ctrl+S"`.
+    Key(HotkeySequence),
+    /// Run the actions of the macro with this name.
+    Macro(String),
+    /// Wait this many milliseconds. Only useful in macros.
+    Sleep(u64),
+}
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("can't read the config file")]
+    Io(#[from] std::io::Error),
+    #[error("invalid config")]
+    Toml(#[from] toml::de::Error),
+    #[error("unknown macro '{0}'")]
+    UnknownMacro(String),
+    #[error("macro '{0}' runs itself")]
+    RecursiveMacro(String),
+}
+
+impl FromStr for Config {
+    type Err = ConfigError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(toml::from_str(s)?)
+    }
+}
+
+impl Config {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        fs::read_to_string(path)?.parse()
+    }
+
+    /// Replaces the binds and remaps of the previously applied config with the ones of this
+    /// config. Nothing changes if the config has an error.
+    ///
+    /// Binds of the config replace other binds of the same keys and buttons.
+    pub fn apply(&self) -> Result<(), ConfigError> {
+        let mut keybd_hotkeys: HashMap<KeybdKey, Vec<HotkeyBind>> = HashMap::new();
+        let mut mouse_hotkeys: HashMap<MouseButton, Vec<HotkeyBind>> = HashMap::new();
+        for (&hotkey, bind) in &self.binds {
+            let bind = HotkeyBind {
+                hotkey,
+                steps: Arc::new(self.steps(&bind.action, &mut Vec::new())?),
+                block: bind.block,
+            };
+            match hotkey.input {
+                Input::Keybd(key) => keybd_hotkeys.entry(key).or_default().push(bind),
+                Input::Mouse(button) => mouse_hotkeys.entry(button).or_default().push(bind),
+            }
+        }
+
+        // Everything is swapped while holding the locks, so that no event sees half of the
+        // old config and half of the new one.
+        let mut applied = APPLIED_CONFIG.lock().unwrap();
+        let mut keybd_binds = KEYBD_BINDS.lock().unwrap();
+        let mut mouse_binds = MOUSE_BINDS.lock().unwrap();
+        let mut remaps = REMAPS.lock().unwrap();
+        for key in applied.keys.drain() {
+            keybd_binds.remove(&key);
+        }
+        for button in applied.buttons.drain() {
+            mouse_binds.remove(&button);
+        }
+        for key in applied.remaps.drain() {
+            remaps.remove(&key);
+        }
+        for (key, hotkeys) in keybd_hotkeys {
+            keybd_binds.insert(key, hotkey_bind(hotkeys));
+            applied.keys.insert(key);
+        }
+        for (button, hotkeys) in mouse_hotkeys {
+            mouse_binds.insert(button, hotkey_bind(hotkeys));
+            applied.buttons.insert(button);
+        }
+        for (&from, &to) in &self.remaps {
+            remaps.insert(from, to);
+            applied.remaps.insert(from);
+        }
+        Ok(())
+    }
+
+    // Resolves macros, so that errors are found before the config is applied.
+    fn steps(
+        &self,
+        action: &ConfigAction,
+        macros: &mut Vec<String>,
+    ) -> Result<Vec<Step>, ConfigError> {
+        Ok(match action {
+            ConfigAction::Text(text) => vec![Step::Text(text.clone())],
+            ConfigAction::Key(hotkeys) => vec![Step::Key(hotkeys.clone())],
+            ConfigAction::Sleep(ms) => vec![Step::Sleep(Duration::from_millis(*ms))],
+            ConfigAction::Macro(name) => {
+                if macros.contains(name) {
+                    return Err(ConfigError::RecursiveMacro(name.clone()));
+                }
+                let actions = self
+                    .macros
+                    .get(name)
+                    .ok_or_else(|| ConfigError::UnknownMacro(name.clone()))?;
+                macros.push(name.clone());
+                let mut steps = Vec::new();
+                for action in actions {
+                    steps.extend(self.steps(action, macros)?);
+                }
+                macros.pop();
+                steps
+            }
+        })
+    }
+}
+
+/// Reads the config file at `path` and applies it, replacing the previously applied
+/// config.
+pub fn load_config<P: AsRef<Path>>(path: P) -> Result<(), ConfigError> {
+    Config::from_file(path)?.apply()
+}
+
+/// Like `load_config`, but also applies the file again whenever it changes, until the
+/// program exits. If a changed file has an error, the previous config stays applied and
+/// `on_error` is called.
+pub fn watch_config<P: AsRef<Path>, F: Fn(ConfigError) + Send + 'static>(
+    path: P,
+    on_error: F,
+) -> Result<(), ConfigError> {
+    let path = path.as_ref().to_owned();
+    load_config(&path)?;
+    let changes = watch_file(&path)?;
+    spawn(move || {
+        for () in changes {
+            if let Err(err) = load_config(&path) {
+                on_error(err);
+            }
+        }
+    });
+    Ok(())
+}
+
+#[derive(Default)]
+struct AppliedConfig {
+    keys: HashSet<KeybdKey>,
+    buttons: HashSet<MouseButton>,
+    remaps: HashSet<KeybdKey>,
+}
+
+static APPLIED_CONFIG: Lazy<Mutex<AppliedConfig>> =
+    Lazy::new(|| Mutex::new(AppliedConfig::default()));
+
+#[derive(Debug, Clone)]
+enum Step {
+    Text(String),
+    Key(HotkeySequence),
+    Sleep(Duration),
+}
+
+struct HotkeyBind {
+    hotkey: Hotkey,
+    steps: Arc<Vec<Step>>,
+    block: bool,
+}
+
+// One bind serves all hotkeys of a key, and runs the one whose modifiers are held.
+fn hotkey_bind(binds: Vec<HotkeyBind>) -> Bind {
+    Bind::Blockable(Arc::new(move |event| {
+        let Some(bind) = binds.iter().find(|bind| bind.hotkey.matches(event)) else {
+            return BlockInput::DontBlock;
+        };
+        let steps = Arc::clone(&bind.steps);
+        spawn(move || {
+            for step in steps.iter() {
+                match step {
+                    Step::Text(text) => KeySequence(text).send(),
+                    Step::Key(hotkeys) => hotkeys.send(),
+                    Step::Sleep(duration) => sleep(*duration),
+                }
+            }
+        });
+        if bind.block {
+            BlockInput::Block
+        } else {
+            BlockInput::DontBlock
+        }
+    }))
+}
+
+#[cfg(target_os = "linux")]
+fn watch_file(path: &Path) -> Result<std::sync::mpsc::Receiver<()>, ConfigError> {
+    use nix::sys::inotify::{AddWatchFlags, InitFlags, Inotify};
+
+    // Editors often replace the file instead of writing to it, so the directory is
+    // watched rather than the file.
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_owned(),
+        _ => PathBuf::from("."),
+    };
+    let name = path.file_name().map(|name| name.to_owned());
+    let inotify = Inotify::init(InitFlags::IN_CLOEXEC).map_err(std::io::Error::from)?;
+    inotify
+        .add_watch(
+            &dir,
+            AddWatchFlags::IN_CLOSE_WRITE | AddWatchFlags::IN_MOVED_TO | AddWatchFlags::IN_CREATE,
+        )
+        .map_err(std::io::Error::from)?;
+    let (sender, receiver) = std::sync::mpsc::channel();
+    spawn(move || {
+        while let Ok(events) = inotify.read_events() {
+            if events.iter().any(|event| event.name == name) && sender.send(()).is_err() {
+                break;
+            }
+        }
+    });
+    Ok(receiver)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn watch_file(path: &Path) -> Result<std::sync::mpsc::Receiver<()>, ConfigError> {
+    let path = path.to_owned();
+    let modified = |path: &PathBuf| fs::metadata(path).and_then(|meta| meta.modified()).ok();
+    let mut last = modified(&path);
+    let (sender, receiver) = std::sync::mpsc::channel();
+    spawn(move || loop {
+        sleep(Duration::from_millis(500));
+        let current = modified(&path);
+        if current != last {
+            last = current;
+            if sender.send(()).is_err() {
+                break;
+            }
+        }
+    });
+    Ok(receiver)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_is_applied_and_replaced() -> Result<(), Box<dyn std::error::Error>> {
+        let _lock = lock_global_state();
+        let key = KeybdKey::OtherKey(0xFFFF_0301);
+        let config: Config = format!(
+            r#"
+            [binds]
+            "{}" = {{ type = "macro", value = "nothing", block = true }}
+
+            [macros]
+            nothing = []
+            "#,
+            key.canonical_name()
+        )
+        .parse()?;
+        config.apply()?;
+        let press = |key| dispatch_keybd_event(key, KeyEvent::Press, false, None);
+        assert_eq!(press(key), BlockInput::Block);
+
+        // A config with an error leaves the applied one in place.
+        let broken: Config =
+            r#"binds = { "F1" = { type = "macro", value = "missing" } }"#.parse()?;
+        assert!(matches!(broken.apply(), Err(ConfigError::UnknownMacro(_))));
+        assert_eq!(press(key), BlockInput::Block);
+
+        Config::default().apply()?;
+        assert_eq!(press(key), BlockInput::DontBlock);
+        Ok(())
+    }
+}
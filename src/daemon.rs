@@ -0,0 +1,415 @@
+use crate::{common::*, hotkey::*, public::*};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    io::{self, BufRead, BufReader, Write},
+    os::unix::{
+        fs::PermissionsExt,
+        net::{UnixListener, UnixStream},
+    },
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::AtomicU64,
+        mpsc::{self, Sender},
+    },
+};
+
+/// Returns the socket the daemon listens on unless told otherwise: `$INPUTBOT_SOCKET` if
+/// set, or `/run/inputbot.sock`.
+pub fn default_socket_path() -> PathBuf {
+    std::env::var_os("INPUTBOT_SOCKET")
+        .map_or_else(|| PathBuf::from("/run/inputbot.sock"), PathBuf::from)
+}
+
+/// A request to the daemon, see `serve`. `cmd` holds the name of the variant, like
+/// `{"cmd": "press", "input": "LeftShift"}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+pub enum DaemonRequest {
+    /// Type the text with `KeySequence`.
+    Type {
+        text: String,
+    },
+    /// Send hotkeys like `"Ctrl+C"` or `"Ctrl+X Ctrl+S"`.
+    Send {
+        hotkeys: HotkeySequence,
+    },
+    /// Press a key or mouse button and leave it down.
+    Press {
+        input: Input,
+    },
+    Release {
+        input: Input,
+    },
+    /// Move the cursor to a position, or by an offset if `relative`.
+    Move {
+        x: i32,
+        y: i32,
+        #[serde(default)]
+        relative: bool,
+    },
+    /// Scroll down this many notches, or up if negative. `horizontal` scrolls right, or
+    /// left if negative.
+    Scroll {
+        notches: i32,
+        #[serde(default)]
+        horizontal: bool,
+    },
+    /// Replied to with whether the key or button is held.
+    IsPressed {
+        input: Input,
+    },
+    /// Replied to with whether a lock key like CapsLock is on.
+    IsToggled {
+        key: KeybdKey,
+    },
+    /// Report presses of the hotkey to this connection with `DaemonMessage::Hotkey`, until
+    /// it's unbound or the connection closes. `block` hides the hotkey's key from other
+    /// programs, which needs `set_grab_keyboards`.
+    Bind {
+        hotkey: Hotkey,
+        #[serde(default)]
+        block: bool,
+    },
+    Unbind {
+        hotkey: Hotkey,
+    },
+    /// Send every key and button event to this connection with `DaemonMessage::Event`.
+    Subscribe,
+    Unsubscribe,
+}
+
+/// A request together with the `id` its reply carries, which is optional.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DaemonRequestLine {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<u64>,
+    #[serde(flatten)]
+    pub request: DaemonRequest,
+}
+
+/// A message from the daemon. `type` holds the name of the variant, like
+/// `{"type": "reply", "id": 1, "value": true}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DaemonMessage {
+    /// Sent for every request, in order, with the `id` of the request. `error` says why
+    /// the request failed, and `value` holds the answer to `is_pressed` and `is_toggled`.
+    Reply {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        id: Option<u64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        value: Option<bool>,
+    },
+    /// A hotkey bound by this connection was pressed.
+    Hotkey { hotkey: Hotkey },
+    /// A key or button event, after `subscribe`.
+    Event { event: InputEvent },
+}
+
+/// Runs the daemon on the Unix socket at `path` until the program exits, reading input
+/// and sending it for any number of client processes. The socket gets the permission bits
+/// `mode`, like `0o660`; anyone who can connect to it can send input.
+///
+/// Clients send requests as one JSON object per line (see `DaemonRequest`), and receive
+/// one JSON object per line (see `DaemonMessage`):
+///
+/// ```text
+/// > {"id": 1, "cmd": "bind", "hotkey": "Ctrl+Alt+T"}
+/// < {"type": "reply", "id": 1}
+/// > {"id": 2, "cmd": "is_pressed", "input": "LeftShift"}
+/// < {"type": "reply", "id": 2, "value": false}
+/// < {"type": "hotkey", "hotkey": "Ctrl+Alt+T"}
+/// > {"id": 3, "cmd": "type", "text": "Hello, world!"}
+/// < {"type": "reply", "id": 3}
+/// ```
+///
+/// The binds and subscription of a connection are removed when it closes.
+pub fn serve<P: AsRef<Path>>(path: P, mode: u32) -> io::Result<()> {
+    let path = path.as_ref();
+    // A socket left behind by a daemon which didn't exit cleanly is replaced, but not one
+    // which is still in use.
+    if path.exists() {
+        if UnixStream::connect(path).is_ok() {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                "another daemon is listening on the socket",
+            ));
+        }
+        fs::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path)?;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))?;
+
+    // The fake device is created up front, so that the first request doesn't wait for it.
+    crate::init_device();
+    set_event_listener(|event| {
+        for sender in SUBSCRIBERS.lock().unwrap().values() {
+            let _ = sender.send(DaemonMessage::Event {
+                event: event.clone(),
+            });
+        }
+    });
+    spawn(|| crate::handle_input_events(false));
+
+    for stream in listener.incoming().flatten() {
+        spawn(move || handle_connection(stream));
+    }
+    Ok(())
+}
+
+struct DaemonBind {
+    connection: u64,
+    hotkey: Hotkey,
+    block: bool,
+    sender: Sender<DaemonMessage>,
+}
+
+static SUBSCRIBERS: Lazy<Mutex<HashMap<u64, Sender<DaemonMessage>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+static DAEMON_BINDS: Lazy<Mutex<HashMap<Input, Vec<DaemonBind>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+pub(crate) fn handle_connection(stream: UnixStream) {
+    static NEXT_CONNECTION: AtomicU64 = AtomicU64::new(0);
+    let connection = NEXT_CONNECTION.fetch_add(1, Ordering::Relaxed);
+
+    let Ok(mut writer) = stream.try_clone() else {
+        return;
+    };
+    let (sender, receiver) = mpsc::channel::<DaemonMessage>();
+    // Messages are written by their own thread, so that a slow client doesn't hold up
+    // events. It stops once the connection's binds and subscription are gone.
+    spawn(move || {
+        for message in receiver {
+            let mut line = serde_json::to_string(&message).unwrap();
+            line.push('\n');
+            if writer.write_all(line.as_bytes()).is_err() {
+                break;
+            }
+        }
+    });
+
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let reply = match serde_json::from_str::<DaemonRequestLine>(&line) {
+            Ok(DaemonRequestLine { id, request }) => {
+                // A panic fails the request rather than the connection, which would leave
+                // its binds and subscription behind.
+                let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                    handle_request(connection, &sender, request)
+                }))
+                .unwrap_or_else(|_| Err("inputbotd failed to handle the request".to_owned()));
+                match result {
+                    Ok(value) => DaemonMessage::Reply {
+                        id,
+                        error: None,
+                        value,
+                    },
+                    Err(error) => DaemonMessage::Reply {
+                        id,
+                        error: Some(error),
+                        value: None,
+                    },
+                }
+            }
+            // The id is still replied to if the rest of the request is invalid.
+            Err(err) => DaemonMessage::Reply {
+                id: serde_json::from_str::<serde_json::Value>(&line)
+                    .ok()
+                    .and_then(|request| request.get("id")?.as_u64()),
+                error: Some(err.to_string()),
+                value: None,
+            },
+        };
+        if sender.send(reply).is_err() {
+            break;
+        }
+    }
+
+    SUBSCRIBERS.lock().unwrap().remove(&connection);
+    let inputs: Vec<Input> = DAEMON_BINDS.lock().unwrap().keys().copied().collect();
+    for input in inputs {
+        remove_binds(input, |bind| bind.connection == connection);
+    }
+}
+
+fn handle_request(
+    connection: u64,
+    sender: &Sender<DaemonMessage>,
+    request: DaemonRequest,
+) -> Result<Option<bool>, String> {
+    match request {
+        DaemonRequest::Type { text } => KeySequence(&text).send(),
+        DaemonRequest::Send { hotkeys } => hotkeys.send(),
+        DaemonRequest::Press { input } => match input {
+            Input::Keybd(key) => key.press(),
+            Input::Mouse(button) => button.press(),
+        },
+        DaemonRequest::Release { input } => match input {
+            Input::Keybd(key) => key.release(),
+            Input::Mouse(button) => button.release(),
+        },
+        DaemonRequest::Move { x, y, relative } => {
+            if relative {
+                MouseCursor::move_rel(x, y);
+            } else {
+                MouseCursor::try_move_abs(x, y).map_err(|err| err.to_string())?;
+            }
+        }
+        DaemonRequest::Scroll {
+            notches,
+            horizontal,
+        } => {
+            if horizontal {
+                MouseWheel::scroll_hor(notches);
+            } else {
+                MouseWheel::scroll_ver(notches);
+            }
+        }
+        DaemonRequest::IsPressed { input } => {
+            return Ok(Some(match input {
+                Input::Keybd(key) => key.is_pressed(),
+                Input::Mouse(button) => button.is_pressed(),
+            }))
+        }
+        DaemonRequest::IsToggled { key } => return Ok(Some(key.is_toggled())),
+        DaemonRequest::Bind { hotkey, block } => {
+            let mut binds = DAEMON_BINDS.lock().unwrap();
+            let hotkeys = binds.entry(hotkey.input).or_default();
+            hotkeys.retain(|bind| !(bind.connection == connection && bind.hotkey == hotkey));
+            hotkeys.push(DaemonBind {
+                connection,
+                hotkey,
+                block,
+                sender: sender.clone(),
+            });
+            install_bind(hotkey.input);
+        }
+        DaemonRequest::Unbind { hotkey } => {
+            if !remove_binds(hotkey.input, |bind| {
+                bind.connection == connection && bind.hotkey == hotkey
+            }) {
+                return Err(format!("'{hotkey}' isn't bound"));
+            }
+        }
+        DaemonRequest::Subscribe => {
+            SUBSCRIBERS
+                .lock()
+                .unwrap()
+                .insert(connection, sender.clone());
+        }
+        DaemonRequest::Unsubscribe => {
+            SUBSCRIBERS.lock().unwrap().remove(&connection);
+        }
+    }
+    Ok(None)
+}
+
+// One bind of the key or button serves the hotkeys of all connections. Binds are installed
+// and removed while holding `DAEMON_BINDS`, so that they stay in step with it.
+fn install_bind(input: Input) {
+    let callback = |event: &InputEvent| {
+        let mut block = BlockInput::DontBlock;
+        if let Some(binds) = DAEMON_BINDS.lock().unwrap().get(&event.input) {
+            for bind in binds.iter().filter(|bind| bind.hotkey.matches(event)) {
+                let _ = bind.sender.send(DaemonMessage::Hotkey {
+                    hotkey: bind.hotkey,
+                });
+                if bind.block {
+                    block = BlockInput::Block;
+                }
+            }
+        }
+        block
+    };
+    match input {
+        Input::Keybd(key) => key.blockable_bind_event(callback),
+        Input::Mouse(button) => button.blockable_bind_event(callback),
+    }
+}
+
+// Removes the binds of `input` which match, and the bind of `input` itself once it has no
+// hotkeys left. Returns whether any were removed.
+fn remove_binds<F: Fn(&DaemonBind) -> bool>(input: Input, matches: F) -> bool {
+    let mut binds = DAEMON_BINDS.lock().unwrap();
+    let Some(hotkeys) = binds.get_mut(&input) else {
+        return false;
+    };
+    let count = hotkeys.len();
+    hotkeys.retain(|bind| !matches(bind));
+    let removed = hotkeys.len() < count;
+    if hotkeys.is_empty() {
+        binds.remove(&input);
+        match input {
+            Input::Keybd(key) => key.unbind(),
+            Input::Mouse(button) => button.unbind(),
+        }
+    }
+    removed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Shutdown;
+
+    #[test]
+    fn daemon_reports_bound_hotkeys() -> Result<(), Box<dyn std::error::Error>> {
+        let _lock = lock_global_state();
+        let (mut client, server) = UnixStream::pair()?;
+        spawn(move || handle_connection(server));
+        let mut lines = BufReader::new(client.try_clone()?).lines();
+        let mut next = || -> Option<DaemonMessage> {
+            Some(serde_json::from_str(&lines.next()?.unwrap()).unwrap())
+        };
+
+        let key = KeybdKey::OtherKey(0xFFFF_0401);
+        writeln!(
+            client,
+            r#"{{"id": 1, "cmd": "bind", "hotkey": "{}", "block": true}}"#,
+            key.canonical_name()
+        )?;
+        assert!(matches!(
+            next(),
+            Some(DaemonMessage::Reply {
+                id: Some(1),
+                error: None,
+                value: None
+            })
+        ));
+        assert_eq!(
+            dispatch_keybd_event(key, KeyEvent::Press, false, None),
+            BlockInput::Block
+        );
+        assert!(matches!(
+            next(),
+            Some(DaemonMessage::Hotkey { hotkey }) if hotkey.input == Input::Keybd(key)
+        ));
+
+        writeln!(client, r#"{{"id": 2, "cmd": "fly"}}"#)?;
+        assert!(matches!(
+            next(),
+            Some(DaemonMessage::Reply {
+                id: Some(2),
+                error: Some(_),
+                ..
+            })
+        ));
+
+        // The binds of a connection go away with it, and then the daemon closes its end.
+        client.shutdown(Shutdown::Write)?;
+        assert!(next().is_none());
+        assert!(!key.is_bound());
+        Ok(())
+    }
+}
@@ -0,0 +1,294 @@
+use crate::public::*;
+use std::{fmt, str::FromStr};
+
+#[cfg(feature = "serde")]
+use serde::{
+    de::{Deserializer, Error},
+    Deserialize, Serialize, Serializer,
+};
+
+/// A key or mouse button together with the modifiers held with it, like `Ctrl+Alt+Delete`
+/// or `LeftSuper+Shift+MouseForward`.
+///
+/// When parsing, `Ctrl`, `Shift`, `Alt` and `Super` (also `Control`, `Win`, `Meta` and
+/// `Cmd`) stand for either side of a modifier, and the name of a modifier key like
+/// `LeftShift` for that side only. Names are case-insensitive.
+#[derive(Debug, Eq, PartialEq, Hash, Copy, Clone)]
+pub struct Hotkey {
+    pub modifiers: Modifiers,
+    pub input: Input,
+}
+
+/// Hotkeys pressed one after another, like `Ctrl+X Ctrl+S`.
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub struct HotkeySequence(pub Vec<Hotkey>);
+
+// Each modifier with the name it's written as when either side will do, and the key sent
+// for it.
+const MODIFIER_GROUPS: [(Modifiers, &str, KeybdKey); 4] = [
+    (Modifiers::CONTROL, "Ctrl", KeybdKey::LControlKey),
+    (Modifiers::ALT, "Alt", KeybdKey::LAltKey),
+    (Modifiers::SHIFT, "Shift", KeybdKey::LShiftKey),
+    (Modifiers::SUPER, "Super", KeybdKey::LSuper),
+];
+
+impl Hotkey {
+    pub fn new(modifiers: Modifiers, input: Input) -> Self {
+        Hotkey { modifiers, input }
+    }
+
+    /// Returns true if `event` is of the hotkey's key or button, and exactly the hotkey's
+    /// modifiers are held. Lock states are ignored.
+    pub fn matches(&self, event: &InputEvent) -> bool {
+        if event.input != self.input {
+            return false;
+        }
+        // A modifier key which is itself the hotkey's key doesn't count as held with it.
+        let mut held = event.modifiers;
+        if let Input::Keybd(key) = event.input {
+            held.remove(Modifiers::of_key(key));
+        }
+        MODIFIER_GROUPS.iter().all(|&(group, _, _)| {
+            let wanted = self.modifiers & group;
+            let held = held & group;
+            if wanted.is_empty() {
+                held.is_empty()
+            } else if wanted == group {
+                !held.is_empty()
+            } else {
+                held.contains(wanted)
+            }
+        })
+    }
+
+    /// Presses the modifiers, taps the key or button, and releases the modifiers.
+    pub fn send(&self) {
+        let modifier_keys: Vec<KeybdKey> = MODIFIER_GROUPS
+            .iter()
+            .flat_map(|&(group, _, either)| match self.modifiers & group {
+                wanted if wanted == group => vec![either],
+                wanted => modifier_keys(wanted),
+            })
+            .collect();
+        for key in &modifier_keys {
+            key.press();
+        }
+        match self.input {
+            Input::Keybd(key) => {
+                key.press();
+                key.release();
+            }
+            Input::Mouse(button) => {
+                button.press();
+                button.release();
+            }
+        }
+        for key in modifier_keys.iter().rev() {
+            key.release();
+        }
+    }
+}
+
+impl HotkeySequence {
+    /// Sends the hotkeys one after another.
+    pub fn send(&self) {
+        for hotkey in &self.0 {
+            hotkey.send();
+        }
+    }
+}
+
+// Returns the modifier keys of one side which set `modifiers`.
+fn modifier_keys(modifiers: Modifiers) -> Vec<KeybdKey> {
+    [
+        KeybdKey::LControlKey,
+        KeybdKey::RControlKey,
+        KeybdKey::LAltKey,
+        KeybdKey::RAltKey,
+        KeybdKey::LShiftKey,
+        KeybdKey::RShiftKey,
+        KeybdKey::LSuper,
+        KeybdKey::RSuper,
+    ]
+    .into_iter()
+    .filter(|&key| modifiers.intersects(Modifiers::of_key(key) - Modifiers::ALTGR))
+    .collect()
+}
+
+impl FromStr for Hotkey {
+    type Err = ParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim().is_empty() {
+            return Err(ParseError::EmptyHotkey);
+        }
+        let mut parts: Vec<&str> = s.split('+').map(str::trim).collect();
+        let input = parts.pop().unwrap_or_default();
+        if input.is_empty() {
+            return Err(ParseError::MissingKey {
+                hotkey: s.to_owned(),
+            });
+        }
+
+        let mut modifiers = Modifiers::empty();
+        for part in parts {
+            modifiers |= match part.to_lowercase().as_str() {
+                "ctrl" | "control" => Modifiers::CONTROL,
+                "alt" | "option" => Modifiers::ALT,
+                "shift" => Modifiers::SHIFT,
+                "super" | "win" | "windows" | "meta" | "cmd" | "command" => Modifiers::SUPER,
+                _ => match KeybdKey::from_str(part).map(Modifiers::of_key) {
+                    Ok(side) if !side.is_empty() => side - Modifiers::ALTGR,
+                    _ => {
+                        return Err(ParseError::UnknownModifier {
+                            name: part.to_owned(),
+                        })
+                    }
+                },
+            };
+        }
+
+        Ok(Hotkey {
+            modifiers,
+            input: input.parse()?,
+        })
+    }
+}
+
+impl FromStr for HotkeySequence {
+    type Err = ParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // Hotkeys are separated by whitespace, but `Ctrl + X` is still one hotkey.
+        let mut hotkeys: Vec<String> = Vec::new();
+        for word in s.split_whitespace() {
+            match hotkeys.last_mut() {
+                Some(last) if last.ends_with('+') || word.starts_with('+') => last.push_str(word),
+                _ => hotkeys.push(word.to_owned()),
+            }
+        }
+        if hotkeys.is_empty() {
+            return Err(ParseError::EmptyHotkey);
+        }
+        hotkeys
+            .iter()
+            .map(|hotkey| hotkey.parse())
+            .collect::<Result<_, _>>()
+            .map(HotkeySequence)
+    }
+}
+
+impl fmt::Display for Hotkey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for &(group, name, _) in &MODIFIER_GROUPS {
+            match self.modifiers & group {
+                wanted if wanted == group => write!(f, "{name}+")?,
+                wanted => {
+                    for key in modifier_keys(wanted) {
+                        write!(f, "{}+", key.canonical_name())?;
+                    }
+                }
+            }
+        }
+        write!(f, "{}", self.input)
+    }
+}
+
+impl fmt::Display for HotkeySequence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, hotkey) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{hotkey}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for Hotkey {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Hotkey {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(Error::custom)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for HotkeySequence {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for HotkeySequence {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hotkeys_parse_and_format() {
+        let hotkey: Hotkey = "control + alt+Return".parse().unwrap();
+        assert_eq!(
+            hotkey,
+            Hotkey::new(
+                Modifiers::CONTROL | Modifiers::ALT,
+                Input::Keybd(KeybdKey::EnterKey)
+            )
+        );
+        assert_eq!(hotkey.to_string(), "Ctrl+Alt+Enter");
+
+        let hotkey: Hotkey = "LeftSuper+Shift+MouseForward".parse().unwrap();
+        assert_eq!(
+            hotkey,
+            Hotkey::new(
+                Modifiers::LSUPER | Modifiers::SHIFT,
+                Input::Mouse(MouseButton::X2Button)
+            )
+        );
+        assert_eq!(hotkey.to_string(), "Shift+LeftSuper+MouseForward");
+        assert_eq!(hotkey.to_string().parse::<Hotkey>().unwrap(), hotkey);
+
+        let sequence: HotkeySequence = "Ctrl+X  Ctrl+S".parse().unwrap();
+        assert_eq!(sequence.0.len(), 2);
+        assert_eq!(sequence.to_string(), "Ctrl+x Ctrl+s");
+
+        assert!(matches!("".parse::<Hotkey>(), Err(ParseError::EmptyHotkey)));
+        assert!(matches!(
+            "Ctrl+".parse::<Hotkey>(),
+            Err(ParseError::MissingKey { .. })
+        ));
+        assert!(matches!(
+            "A+B".parse::<Hotkey>(),
+            Err(ParseError::UnknownModifier { .. })
+        ));
+        assert!(matches!(
+            "Ctrl+Nope".parse::<Hotkey>(),
+            Err(ParseError::UnknownKey { .. })
+        ));
+    }
+}
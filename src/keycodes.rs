@@ -0,0 +1,376 @@
+use crate::public::{KeybdKey, KeybdKey::*};
+
+// The codes the platforms identify keys with. They are plain tables rather than conversions
+// of the platform modules, so that the tests check every platform's codes on any platform.
+
+// https://docs.microsoft.com/en-us/windows/win32/inputdev/virtual-key-codes?redirectedfrom=MSDN
+// The IME keys have the codes the Japanese and Korean layouts give them.
+#[cfg_attr(not(any(target_os = "windows", test)), allow(dead_code))]
+pub(crate) const VIRTUAL_KEYS: &[(KeybdKey, u64)] = &[
+    (BackspaceKey, 0x08),
+    (TabKey, 0x09),
+    (EnterKey, 0x0D),
+    (EscapeKey, 0x1B),
+    (SpaceKey, 0x20),
+    (PageUpKey, 0x21),
+    (PageDownKey, 0x22),
+    (EndKey, 0x23),
+    (HomeKey, 0x24),
+    (LeftKey, 0x25),
+    (UpKey, 0x26),
+    (RightKey, 0x27),
+    (DownKey, 0x28),
+    (InsertKey, 0x2D),
+    (DeleteKey, 0x2E),
+    (PrintScreenKey, 0x2C),
+    (PauseKey, 0x13),
+    (MenuKey, 0x5D),
+    (Numrow0Key, 0x30),
+    (Numrow1Key, 0x31),
+    (Numrow2Key, 0x32),
+    (Numrow3Key, 0x33),
+    (Numrow4Key, 0x34),
+    (Numrow5Key, 0x35),
+    (Numrow6Key, 0x36),
+    (Numrow7Key, 0x37),
+    (Numrow8Key, 0x38),
+    (Numrow9Key, 0x39),
+    (AKey, 0x41),
+    (BKey, 0x42),
+    (CKey, 0x43),
+    (DKey, 0x44),
+    (EKey, 0x45),
+    (FKey, 0x46),
+    (GKey, 0x47),
+    (HKey, 0x48),
+    (IKey, 0x49),
+    (JKey, 0x4A),
+    (KKey, 0x4B),
+    (LKey, 0x4C),
+    (MKey, 0x4D),
+    (NKey, 0x4E),
+    (OKey, 0x4F),
+    (PKey, 0x50),
+    (QKey, 0x51),
+    (RKey, 0x52),
+    (SKey, 0x53),
+    (TKey, 0x54),
+    (UKey, 0x55),
+    (VKey, 0x56),
+    (WKey, 0x57),
+    (XKey, 0x58),
+    (YKey, 0x59),
+    (ZKey, 0x5A),
+    (LSuper, 0x5B),
+    (RSuper, 0x5C),
+    (Numpad0Key, 0x60),
+    (Numpad1Key, 0x61),
+    (Numpad2Key, 0x62),
+    (Numpad3Key, 0x63),
+    (Numpad4Key, 0x64),
+    (Numpad5Key, 0x65),
+    (Numpad6Key, 0x66),
+    (Numpad7Key, 0x67),
+    (Numpad8Key, 0x68),
+    (Numpad9Key, 0x69),
+    (NumpadPlusKey, 0x6B),
+    (NumpadMinusKey, 0x6D),
+    (NumpadMultiplyKey, 0x6A),
+    (NumpadDivideKey, 0x6F),
+    (NumpadDecimalKey, 0x6E),
+    // Same as Enter, told apart by the extended flag. Enter comes first, so that the code
+    // reads as Enter.
+    (NumpadEnterKey, 0x0D),
+    (F1Key, 0x70),
+    (F2Key, 0x71),
+    (F3Key, 0x72),
+    (F4Key, 0x73),
+    (F5Key, 0x74),
+    (F6Key, 0x75),
+    (F7Key, 0x76),
+    (F8Key, 0x77),
+    (F9Key, 0x78),
+    (F10Key, 0x79),
+    (F11Key, 0x7A),
+    (F12Key, 0x7B),
+    (F13Key, 0x7C),
+    (F14Key, 0x7D),
+    (F15Key, 0x7E),
+    (F16Key, 0x7F),
+    (F17Key, 0x80),
+    (F18Key, 0x81),
+    (F19Key, 0x82),
+    (F20Key, 0x83),
+    (F21Key, 0x84),
+    (F22Key, 0x85),
+    (F23Key, 0x86),
+    (F24Key, 0x87),
+    (NumLockKey, 0x90),
+    (ScrollLockKey, 0x91),
+    (CapsLockKey, 0x14),
+    (LShiftKey, 0xA0),
+    (RShiftKey, 0xA1),
+    (LControlKey, 0xA2),
+    (RControlKey, 0xA3),
+    (LAltKey, 0xA4),
+    (RAltKey, 0xA5),
+    (BrowserBackKey, 0xA6),
+    (BrowserForwardKey, 0xA7),
+    (BrowserRefreshKey, 0xA8),
+    (BrowserStopKey, 0xA9),
+    (BrowserSearchKey, 0xAA),
+    (BrowserFavoritesKey, 0xAB),
+    (BrowserHomeKey, 0xAC),
+    (VolumeMuteKey, 0xAD),
+    (VolumeDownKey, 0xAE),
+    (VolumeUpKey, 0xAF),
+    (MediaNextTrackKey, 0xB0),
+    (MediaPrevTrackKey, 0xB1),
+    (MediaStopKey, 0xB2),
+    (MediaPlayPauseKey, 0xB3),
+    (LaunchMailKey, 0xB4),
+    (LaunchMediaKey, 0xB5),
+    (LaunchApp1Key, 0xB6),
+    (LaunchApp2Key, 0xB7),
+    (BackquoteKey, 0xC0),
+    (SlashKey, 0xBF),
+    (BackslashKey, 0xDC),
+    (CommaKey, 0xBC),
+    (PeriodKey, 0xBE),
+    (MinusKey, 0xBD),
+    (QuoteKey, 0xDE),
+    (SemicolonKey, 0xBA),
+    (LBracketKey, 0xDB),
+    (RBracketKey, 0xDD),
+    (EqualKey, 0xBB),
+    (IntlBackslashKey, 0xE2),
+    (KanaKey, 0xF2),
+    // The key alternates between the two codes.
+    (ZenkakuHankakuKey, 0xF3),
+    (ZenkakuHankakuKey, 0xF4),
+    (ConvertKey, 0x1C),
+    (NonConvertKey, 0x1D),
+    (HangulKey, 0x15),
+    (HanjaKey, 0x19),
+];
+
+// https://www.cl.cam.ac.uk/~mgk25/ucs/keysymdef.h
+// https://github.com/AltF02/x11-rs/blob/master/src/keysym.rs
+#[cfg_attr(not(any(target_os = "linux", test)), allow(dead_code))]
+pub(crate) const KEYSYMS: &[(KeybdKey, u64)] = &[
+    (BackspaceKey, 0xFF08),
+    (TabKey, 0xFF09),
+    (EnterKey, 0xFF0D),
+    (EscapeKey, 0xFF1B),
+    (SpaceKey, 0x020),
+    (PageUpKey, 0xFF55),
+    (PageDownKey, 0xFF56),
+    (EndKey, 0xFF57),
+    (HomeKey, 0xFF50),
+    (LeftKey, 0xFF51),
+    (UpKey, 0xFF52),
+    (RightKey, 0xFF53),
+    (DownKey, 0xFF54),
+    (InsertKey, 0xFF63),
+    (DeleteKey, 0xFFFF),
+    (PrintScreenKey, 0xFF61),
+    (PauseKey, 0xFF13),
+    (MenuKey, 0xFF67),
+    (Numrow0Key, 0x030),
+    (Numrow1Key, 0x031),
+    (Numrow2Key, 0x032),
+    (Numrow3Key, 0x033),
+    (Numrow4Key, 0x034),
+    (Numrow5Key, 0x035),
+    (Numrow6Key, 0x036),
+    (Numrow7Key, 0x037),
+    (Numrow8Key, 0x038),
+    (Numrow9Key, 0x039),
+    (AKey, 0x041),
+    (BKey, 0x042),
+    (CKey, 0x043),
+    (DKey, 0x044),
+    (EKey, 0x045),
+    (FKey, 0x046),
+    (GKey, 0x047),
+    (HKey, 0x048),
+    (IKey, 0x049),
+    (JKey, 0x04A),
+    (KKey, 0x04B),
+    (LKey, 0x04C),
+    (MKey, 0x04D),
+    (NKey, 0x04E),
+    (OKey, 0x04F),
+    (PKey, 0x050),
+    (QKey, 0x051),
+    (RKey, 0x052),
+    (SKey, 0x053),
+    (TKey, 0x054),
+    (UKey, 0x055),
+    (VKey, 0x056),
+    (WKey, 0x057),
+    (XKey, 0x058),
+    (YKey, 0x059),
+    (ZKey, 0x05A),
+    (LSuper, 0xFFEB),
+    (RSuper, 0xFFEC),
+    (Numpad0Key, 0xFFB0),
+    (Numpad1Key, 0xFFB1),
+    (Numpad2Key, 0xFFB2),
+    (Numpad3Key, 0xFFB3),
+    (Numpad4Key, 0xFFB4),
+    (Numpad5Key, 0xFFB5),
+    (Numpad6Key, 0xFFB6),
+    (Numpad7Key, 0xFFB7),
+    (Numpad8Key, 0xFFB8),
+    (Numpad9Key, 0xFFB9),
+    (NumpadPlusKey, 0xFFAB),
+    (NumpadMinusKey, 0xFFAD),
+    (NumpadMultiplyKey, 0xFFAA),
+    (NumpadDivideKey, 0xFFAF),
+    (NumpadDecimalKey, 0xFFAE),
+    (NumpadEnterKey, 0xFF8D),
+    (F1Key, 0xFFBE),
+    (F2Key, 0xFFBF),
+    (F3Key, 0xFFC0),
+    (F4Key, 0xFFC1),
+    (F5Key, 0xFFC2),
+    (F6Key, 0xFFC3),
+    (F7Key, 0xFFC4),
+    (F8Key, 0xFFC5),
+    (F9Key, 0xFFC6),
+    (F10Key, 0xFFC7),
+    (F11Key, 0xFFC8),
+    (F12Key, 0xFFC9),
+    (F13Key, 0xFFCA),
+    (F14Key, 0xFFCB),
+    (F15Key, 0xFFCC),
+    (F16Key, 0xFFCD),
+    (F17Key, 0xFFCE),
+    (F18Key, 0xFFCF),
+    (F19Key, 0xFFD0),
+    (F20Key, 0xFFD1),
+    (F21Key, 0xFFD2),
+    (F22Key, 0xFFD3),
+    (F23Key, 0xFFD4),
+    (F24Key, 0xFFD5),
+    (NumLockKey, 0xFF7F),
+    (ScrollLockKey, 0xFF14),
+    (CapsLockKey, 0xFFE5),
+    (LShiftKey, 0xFFE1),
+    (RShiftKey, 0xFFE2),
+    (LControlKey, 0xFFE3),
+    (RControlKey, 0xFFE4),
+    (LAltKey, 0xFFE9),
+    (RAltKey, 0xFFEA),
+    (BrowserBackKey, 0x1008FF26),
+    (BrowserForwardKey, 0x1008FF27),
+    (BrowserRefreshKey, 0x1008FF29),
+    (BrowserStopKey, 0x1008FF28),
+    (BrowserSearchKey, 0x1008FF1B),
+    (BrowserFavoritesKey, 0x1008FF30),
+    (BrowserHomeKey, 0x1008FF18),
+    (VolumeMuteKey, 0x1008FF12),
+    (VolumeDownKey, 0x1008FF11),
+    (VolumeUpKey, 0x1008FF13),
+    (MediaNextTrackKey, 0x1008FF17),
+    (MediaPrevTrackKey, 0x1008FF16),
+    (MediaStopKey, 0x1008FF15),
+    (MediaPlayPauseKey, 0x1008FF14),
+    (LaunchMailKey, 0x1008FF19),
+    (LaunchMediaKey, 0x1008FF32),
+    (LaunchApp1Key, 0x1008FF33),
+    (LaunchApp2Key, 0x1008FF1D),
+    (BackquoteKey, 0x060),
+    (SlashKey, 0x02F),
+    (BackslashKey, 0x05C),
+    (CommaKey, 0x02C),
+    (PeriodKey, 0x02E),
+    (MinusKey, 0x02D),
+    (QuoteKey, 0x027),
+    (SemicolonKey, 0x03B),
+    (LBracketKey, 0x05B),
+    (RBracketKey, 0x05D),
+    (EqualKey, 0x03D),
+    (IntlBackslashKey, 0x03C),
+    (KanaKey, 0xFF27),
+    (ZenkakuHankakuKey, 0xFF2A),
+    (ConvertKey, 0xFF23),
+    (NonConvertKey, 0xFF22),
+    (HangulKey, 0xFF31),
+    (HanjaKey, 0xFF34),
+];
+
+// Returns the first code of `key` in `table`.
+pub(crate) fn code_of(table: &[(KeybdKey, u64)], key: KeybdKey) -> Option<u64> {
+    table
+        .iter()
+        .find(|&&(other, _)| other == key)
+        .map(|&(_, code)| code)
+}
+
+// Returns the key of `code` in `table`.
+pub(crate) fn key_of(table: &[(KeybdKey, u64)], code: u64) -> Option<KeybdKey> {
+    table
+        .iter()
+        .find(|&&(_, other)| other == code)
+        .map(|&(key, _)| key)
+}
+
+// Returns the key of a virtual-key code from the keyboard hook. Numpad Enter shares the code
+// of Enter, and only the hook's extended flag tells them apart.
+#[cfg_attr(not(any(target_os = "windows", test)), allow(dead_code))]
+pub(crate) fn key_of_virtual_key(code: u64, extended: bool) -> KeybdKey {
+    match key_of(VIRTUAL_KEYS, code).unwrap_or(OtherKey(code)) {
+        EnterKey if extended => NumpadEnterKey,
+        key => key,
+    }
+}
+
+// Returns the virtual-key codes which are `key`'s alone, so that `GetAsyncKeyState` of one of
+// them means `key` is down. Numpad Enter has none.
+#[cfg_attr(not(any(target_os = "windows", test)), allow(dead_code))]
+pub(crate) fn own_virtual_keys(key: KeybdKey) -> impl Iterator<Item = u64> {
+    VIRTUAL_KEYS
+        .iter()
+        .filter(move |&&(other, code)| other == key && key_of(VIRTUAL_KEYS, code) == Some(key))
+        .map(|&(_, code)| code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use strum::IntoEnumIterator;
+
+    #[test]
+    fn every_key_has_a_platform_code() {
+        for key in KeybdKey::iter() {
+            assert!(key.physical_key().is_some(), "{key:?} has no evdev code");
+
+            let keysym = code_of(KEYSYMS, key).unwrap_or_else(|| panic!("{key:?} has no keysym"));
+            assert_eq!(key_of(KEYSYMS, keysym), Some(key));
+
+            let vk = code_of(VIRTUAL_KEYS, key)
+                .unwrap_or_else(|| panic!("{key:?} has no virtual-key code"));
+            // Numpad Enter shares the virtual-key code of Enter.
+            if key == NumpadEnterKey {
+                assert_eq!(key_of(VIRTUAL_KEYS, vk), Some(EnterKey));
+                assert_eq!(key_of_virtual_key(vk, true), key);
+                assert_eq!(own_virtual_keys(key).next(), None);
+            } else {
+                assert_eq!(key_of(VIRTUAL_KEYS, vk), Some(key));
+                assert!(own_virtual_keys(key).any(|code| code == vk), "{key:?}");
+            }
+        }
+        assert_eq!(key_of(VIRTUAL_KEYS, 0xF4), Some(ZenkakuHankakuKey));
+        assert_eq!(key_of_virtual_key(0x0D, false), EnterKey);
+        assert_eq!(key_of_virtual_key(0x26, true), UpKey);
+        assert_eq!(key_of_virtual_key(0xFFFF, true), OtherKey(0xFFFF));
+        assert_eq!(code_of(KEYSYMS, OtherKey(0x2FF)), None);
+        assert_eq!(key_of(KEYSYMS, 0x2FF), None);
+
+        assert_eq!("NumPadEnter".parse::<KeybdKey>().unwrap(), NumpadEnterKey);
+        assert_eq!("PrtSc".parse::<KeybdKey>().unwrap(), PrintScreenKey);
+        assert_eq!(NumpadPlusKey.display_name(), "Num +");
+    }
+}
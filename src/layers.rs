@@ -0,0 +1,293 @@
+use crate::{common::*, public::*};
+use once_cell::sync::Lazy;
+
+/// The layer which is always at the bottom of the layer stack. Keys which switch to other
+/// layers are usually defined here.
+pub const BASE_LAYER: &str = "base";
+
+/// What a key does while its layer is the topmost active layer defining it.
+#[derive(Clone)]
+pub enum LayerAction {
+    /// Press and release another key in place of this one.
+    Key(KeybdKey),
+    /// Run a callback when the key is pressed.
+    Callback(Handler),
+    /// Hide the key from the rest of the system.
+    Block,
+    /// Activate the named layer while the key is held.
+    Momentary(String),
+    /// Activate the named layer on one press, and deactivate it on the next.
+    Toggle(String),
+    /// Activate the named layer for the next key press only.
+    OneShot(String),
+}
+
+/// A set of key actions which replace the normal behaviour of those keys while the layer
+/// is active. Keys the layer doesn't define fall through to the layers below it, and
+/// finally to the normal binds.
+#[derive(Clone, Default)]
+pub struct Layer {
+    actions: HashMap<KeybdKey, LayerAction>,
+}
+
+impl Layer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn action(mut self, key: KeybdKey, action: LayerAction) -> Self {
+        self.actions.insert(key, action);
+        self
+    }
+
+    pub fn map(self, key: KeybdKey, to: KeybdKey) -> Self {
+        self.action(key, LayerAction::Key(to))
+    }
+
+    pub fn bind<F: Fn() + Send + Sync + 'static>(self, key: KeybdKey, callback: F) -> Self {
+        self.action(key, LayerAction::Callback(Arc::new(callback)))
+    }
+
+    pub fn block(self, key: KeybdKey) -> Self {
+        self.action(key, LayerAction::Block)
+    }
+
+    pub fn momentary(self, key: KeybdKey, layer: &str) -> Self {
+        self.action(key, LayerAction::Momentary(layer.to_owned()))
+    }
+
+    pub fn toggle(self, key: KeybdKey, layer: &str) -> Self {
+        self.action(key, LayerAction::Toggle(layer.to_owned()))
+    }
+
+    pub fn one_shot(self, key: KeybdKey, layer: &str) -> Self {
+        self.action(key, LayerAction::OneShot(layer.to_owned()))
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+enum Activation {
+    Base,
+    Momentary(KeybdKey),
+    Toggle,
+    OneShot,
+}
+
+struct ActiveLayer {
+    name: String,
+    activation: Activation,
+}
+
+struct LayerState {
+    stack: Vec<ActiveLayer>,
+    // Keys which were handled by a layer when pressed, and the action they resolved to.
+    // Repeats and the release go to the same action even if the stack changed since.
+    held: HashMap<KeybdKey, LayerAction>,
+}
+
+pub(crate) static LAYERS: Lazy<Mutex<HashMap<String, Layer>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+static LAYER_STATE: Lazy<Mutex<LayerState>> = Lazy::new(|| {
+    Mutex::new(LayerState {
+        stack: vec![ActiveLayer {
+            name: BASE_LAYER.to_owned(),
+            activation: Activation::Base,
+        }],
+        held: HashMap::new(),
+    })
+});
+
+/// Defines or replaces the layer called `name`.
+pub fn define_layer(name: &str, layer: Layer) {
+    LAYERS.lock().unwrap().insert(name.to_owned(), layer);
+}
+
+/// Removes the layer called `name` and deactivates it.
+pub fn remove_layer(name: &str) {
+    LAYERS.lock().unwrap().remove(name);
+    deactivate_layer(name);
+}
+
+/// Activates the layer called `name` until `deactivate_layer` is called or it is toggled
+/// off by a key.
+pub fn activate_layer(name: &str) {
+    let mut state = LAYER_STATE.lock().unwrap();
+    if !state
+        .stack
+        .iter()
+        .any(|active| active.name == name && active.activation == Activation::Toggle)
+    {
+        state.stack.push(ActiveLayer {
+            name: name.to_owned(),
+            activation: Activation::Toggle,
+        });
+    }
+}
+
+/// Deactivates the layer called `name`, however it was activated. The base layer can't be
+/// deactivated.
+pub fn deactivate_layer(name: &str) {
+    LAYER_STATE
+        .lock()
+        .unwrap()
+        .stack
+        .retain(|active| active.activation == Activation::Base || active.name != name);
+}
+
+/// Returns the names of the active layers, from the base layer up to the topmost one.
+pub fn active_layers() -> Vec<String> {
+    LAYER_STATE
+        .lock()
+        .unwrap()
+        .stack
+        .iter()
+        .map(|active| active.name.clone())
+        .collect()
+}
+
+/// Runs the action the active layers define for `key`, and returns true if the event was
+/// consumed.
+pub(crate) fn process_layers(key: KeybdKey, event: KeyEvent) -> bool {
+    let layers = LAYERS.lock().unwrap();
+    let mut state = LAYER_STATE.lock().unwrap();
+    match event {
+        KeyEvent::Press => {
+            let action = state
+                .stack
+                .iter()
+                .rev()
+                .find_map(|active| layers.get(&active.name)?.actions.get(&key))
+                .cloned();
+            // One-shot layers last until the next key which doesn't switch layers.
+            if !matches!(
+                action,
+                Some(LayerAction::Momentary(_) | LayerAction::Toggle(_) | LayerAction::OneShot(_))
+            ) {
+                state
+                    .stack
+                    .retain(|active| active.activation != Activation::OneShot);
+            }
+            let Some(action) = action else {
+                return false;
+            };
+            match &action {
+                LayerAction::Key(to) => inject_keybd_event(*to, event),
+                LayerAction::Callback(cb) => {
+                    let cb = Arc::clone(cb);
+                    spawn(move || cb());
+                }
+                LayerAction::Block => {}
+                LayerAction::Momentary(name) => state.stack.push(ActiveLayer {
+                    name: name.clone(),
+                    activation: Activation::Momentary(key),
+                }),
+                LayerAction::Toggle(name) => {
+                    match state.stack.iter().position(|active| {
+                        active.name == *name && active.activation == Activation::Toggle
+                    }) {
+                        Some(i) => {
+                            state.stack.remove(i);
+                        }
+                        None => state.stack.push(ActiveLayer {
+                            name: name.clone(),
+                            activation: Activation::Toggle,
+                        }),
+                    }
+                }
+                LayerAction::OneShot(name) => state.stack.push(ActiveLayer {
+                    name: name.clone(),
+                    activation: Activation::OneShot,
+                }),
+            }
+            state.held.insert(key, action);
+            true
+        }
+        KeyEvent::Repeat => match state.held.get(&key) {
+            Some(LayerAction::Key(to)) => {
+                inject_keybd_event(*to, event);
+                true
+            }
+            Some(_) => true,
+            None => false,
+        },
+        KeyEvent::Release => match state.held.remove(&key) {
+            Some(LayerAction::Key(to)) => {
+                inject_keybd_event(to, event);
+                true
+            }
+            Some(LayerAction::Momentary(_)) => {
+                state
+                    .stack
+                    .retain(|active| active.activation != Activation::Momentary(key));
+                true
+            }
+            Some(_) => true,
+            None => false,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{sync::mpsc, time::Duration};
+
+    #[test]
+    fn layers_switch_and_resolve() {
+        let _lock = lock_global_state();
+        let momentary = KeybdKey::OtherKey(0xFFFF_0101);
+        let toggle = KeybdKey::OtherKey(0xFFFF_0102);
+        let one_shot = KeybdKey::OtherKey(0xFFFF_0103);
+        let key = KeybdKey::OtherKey(0xFFFF_0104);
+        let (sender, receiver) = mpsc::channel();
+
+        define_layer(
+            BASE_LAYER,
+            Layer::new()
+                .momentary(momentary, "nav")
+                .toggle(toggle, "sym")
+                .one_shot(one_shot, "nav"),
+        );
+        for name in ["nav", "sym"] {
+            let sender = sender.clone();
+            define_layer(
+                name,
+                Layer::new().bind(key, move || sender.send(name).unwrap()),
+            );
+        }
+        let tap = |key| {
+            dispatch_keybd_event(key, KeyEvent::Press, false, None);
+            dispatch_keybd_event(key, KeyEvent::Release, false, None);
+        };
+        // Callbacks run on their own threads.
+        let ran = || receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+
+        dispatch_keybd_event(momentary, KeyEvent::Press, false, None);
+        assert_eq!(active_layers(), [BASE_LAYER, "nav"]);
+        tap(key);
+        assert_eq!(ran(), "nav");
+        dispatch_keybd_event(momentary, KeyEvent::Release, false, None);
+        assert_eq!(active_layers(), [BASE_LAYER]);
+
+        tap(toggle);
+        assert_eq!(active_layers(), [BASE_LAYER, "sym"]);
+        tap(key);
+        assert_eq!(ran(), "sym");
+        tap(toggle);
+        assert_eq!(active_layers(), [BASE_LAYER]);
+
+        tap(one_shot);
+        assert_eq!(active_layers(), [BASE_LAYER, "nav"]);
+        tap(key);
+        assert_eq!(ran(), "nav");
+        assert_eq!(active_layers(), [BASE_LAYER]);
+
+        // Without a layer defining it, the key runs nothing.
+        tap(key);
+        assert!(receiver.recv_timeout(Duration::from_millis(50)).is_err());
+
+        for name in [BASE_LAYER, "nav", "sym"] {
+            remove_layer(name);
+        }
+    }
+}
@@ -3,6 +3,29 @@
 
 mod common;
 
+#[cfg(feature = "serde")]
+mod config;
+#[cfg(feature = "serde")]
+pub use crate::config::*;
+
+#[cfg(all(feature = "daemon", target_os = "linux"))]
+mod daemon;
+#[cfg(all(feature = "daemon", target_os = "linux"))]
+pub use crate::daemon::*;
+#[cfg(all(feature = "daemon", target_os = "linux"))]
+pub mod client;
+
+mod hotkey;
+pub use crate::hotkey::*;
+
+mod keycodes;
+
+mod layers;
+pub use crate::layers::*;
+
+mod physical;
+pub use crate::physical::*;
+
 mod public;
 pub use crate::public::*;
 
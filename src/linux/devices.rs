@@ -0,0 +1,240 @@
+use crate::{common::*, linux::evdev, public::*};
+use input::{Device, DeviceCapability};
+use once_cell::sync::Lazy;
+use std::fs::{read_dir, File};
+
+/// An input device, see `devices`.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct InputDevice {
+    /// The name the device reports, e.g. "AT Translated Set 2 keyboard".
+    pub name: String,
+    /// The name of the device node, e.g. "event5". It can change when the device is
+    /// plugged in again.
+    pub sysname: String,
+    pub vendor_id: u16,
+    pub product_id: u16,
+    pub capabilities: DeviceCapabilities,
+}
+
+#[derive(Debug, Default, Eq, PartialEq, Hash, Copy, Clone)]
+pub struct DeviceCapabilities {
+    pub keyboard: bool,
+    pub pointer: bool,
+}
+
+/// A device which was plugged in or unplugged, see `set_hotplug_listener`.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum HotplugEvent {
+    Added(InputDevice),
+    Removed(InputDevice),
+}
+
+/// Selects the devices a device bind applies to, see `KeybdKey::bind_on_device`.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum DeviceFilter {
+    Name(String),
+    Sysname(String),
+    Id { vendor_id: u16, product_id: u16 },
+}
+
+impl DeviceFilter {
+    pub fn matches(&self, device: &InputDevice) -> bool {
+        match self {
+            DeviceFilter::Name(name) => device.name == *name,
+            DeviceFilter::Sysname(sysname) => device.sysname == *sysname,
+            DeviceFilter::Id {
+                vendor_id,
+                product_id,
+            } => device.vendor_id == *vendor_id && device.product_id == *product_id,
+        }
+    }
+}
+
+impl From<&Device> for InputDevice {
+    fn from(device: &Device) -> Self {
+        InputDevice {
+            name: device.name().to_owned(),
+            sysname: device.sysname().to_owned(),
+            vendor_id: device.id_vendor() as u16,
+            product_id: device.id_product() as u16,
+            capabilities: DeviceCapabilities {
+                keyboard: device.has_capability(DeviceCapability::Keyboard),
+                pointer: device.has_capability(DeviceCapability::Pointer),
+            },
+        }
+    }
+}
+
+/// Returns the input devices which can be read, in the order of their device nodes.
+/// Devices which can't be opened, usually for lack of permissions, are left out.
+pub fn devices() -> Vec<InputDevice> {
+    let Ok(entries) = read_dir("/dev/input") else {
+        return Vec::new();
+    };
+    let mut devices: Vec<InputDevice> = entries
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let sysname = entry.file_name().to_string_lossy().into_owned();
+            if !sysname.starts_with("event") {
+                return None;
+            }
+            let file = File::open(entry.path()).ok()?;
+            let id = evdev::id(&file);
+            Some(InputDevice {
+                name: evdev::name(&file),
+                sysname,
+                vendor_id: id.vendor,
+                product_id: id.product,
+                capabilities: DeviceCapabilities {
+                    keyboard: evdev::has_keys(&file),
+                    pointer: evdev::has_pointer(&file),
+                },
+            })
+        })
+        .collect();
+    devices.sort_by_key(|device| device.sysname["event".len()..].parse::<u32>().ok());
+    devices
+}
+
+type HotplugListener = Arc<dyn Fn(&HotplugEvent) + Send + Sync + 'static>;
+
+static HOTPLUG_LISTENER: Lazy<Mutex<Option<HotplugListener>>> = Lazy::new(|| Mutex::new(None));
+// Hotplug events are delivered in order from a thread of their own, like input events.
+static HOTPLUG_EVENTS: Lazy<Mutex<Sender<HotplugEvent>>> = Lazy::new(|| {
+    let (sender, receiver) = channel::<HotplugEvent>();
+    spawn(move || {
+        for event in receiver {
+            let listener = HOTPLUG_LISTENER.lock().unwrap().clone();
+            if let Some(listener) = listener {
+                listener(&event);
+            }
+        }
+    });
+    Mutex::new(sender)
+});
+
+/// Calls `callback` whenever an input device is added or removed while
+/// `handle_input_events` runs. Devices which are present when it starts are reported as
+/// added, so configuration can be applied in one place. Replaces any previous listener.
+pub fn set_hotplug_listener<F: Fn(&HotplugEvent) + Send + Sync + 'static>(callback: F) {
+    *HOTPLUG_LISTENER.lock().unwrap() = Some(Arc::new(callback));
+}
+
+pub fn remove_hotplug_listener() {
+    *HOTPLUG_LISTENER.lock().unwrap() = None;
+}
+
+pub(crate) fn notify_hotplug_listener(event: HotplugEvent) {
+    if HOTPLUG_LISTENER.lock().unwrap().is_some() {
+        let _ = HOTPLUG_EVENTS.lock().unwrap().send(event);
+    }
+}
+
+// Keys which are down and were handled by a device bind, and whether their press was
+// blocked. Their repeats and release skip the other binds as well.
+static DEVICE_BOUND_DOWN: Lazy<Mutex<HashMap<KeybdKey, BlockInput>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+impl KeybdKey {
+    /// Binds `callback` to this key on the devices matching `device`. Events of the key
+    /// from those devices run this bind in place of the key's other binds, layers and
+    /// remaps.
+    pub fn bind_on_device<F: Fn() + Send + Sync + 'static>(
+        self,
+        device: DeviceFilter,
+        callback: F,
+    ) {
+        self.bind_event_on_device(device, move |_| callback());
+    }
+
+    /// Like `bind_on_device`, but the callback receives the event which fired it.
+    pub fn bind_event_on_device<F: Fn(&InputEvent) + Send + Sync + 'static>(
+        self,
+        device: DeviceFilter,
+        callback: F,
+    ) {
+        KEYBD_DEVICE_BINDS
+            .lock()
+            .unwrap()
+            .insert((device, self), Bind::Normal(Arc::new(callback)));
+    }
+
+    /// Like `bind_on_device`, but blocks the key on those devices. This needs
+    /// `set_grab_keyboards`.
+    pub fn block_bind_on_device<F: Fn() + Send + Sync + 'static>(
+        self,
+        device: DeviceFilter,
+        callback: F,
+    ) {
+        KEYBD_DEVICE_BINDS.lock().unwrap().insert(
+            (device, self),
+            Bind::Block(Arc::new(move |_: &InputEvent| callback())),
+        );
+    }
+
+    /// Like `bind_on_device`, but the callback decides whether to block the key. This
+    /// needs `set_grab_keyboards`.
+    pub fn blockable_bind_on_device<F: Fn() -> BlockInput + Send + Sync + 'static>(
+        self,
+        device: DeviceFilter,
+        callback: F,
+    ) {
+        KEYBD_DEVICE_BINDS.lock().unwrap().insert(
+            (device, self),
+            Bind::Blockable(Arc::new(move |_: &InputEvent| callback())),
+        );
+    }
+
+    pub fn unbind_on_device(self, device: DeviceFilter) {
+        KEYBD_DEVICE_BINDS.lock().unwrap().remove(&(device, self));
+    }
+}
+
+/// Runs the device bind of `key` if the event is one, and dispatches it as usual
+/// otherwise. Generated repeats have no `device`, only the name of the device which
+/// pressed the key.
+pub(crate) fn dispatch_device_keybd_event(
+    device: Option<&Device>,
+    device_name: &str,
+    key: KeybdKey,
+    event: KeyEvent,
+    injected: bool,
+) -> BlockInput {
+    let input_event = lazy_input_event(Input::Keybd(key), event, injected, Some(device_name));
+    let mut bound_down = DEVICE_BOUND_DOWN.lock().unwrap();
+    let block = match event {
+        KeyEvent::Press => device.and_then(|device| {
+            let device = InputDevice::from(device);
+            let bind = KEYBD_DEVICE_BINDS
+                .lock()
+                .unwrap()
+                .iter()
+                .find(|((filter, bound_key), _)| *bound_key == key && filter.matches(&device))
+                .map(|(_, bind)| bind.clone())?;
+            let block = match bind {
+                Bind::Normal(cb) => {
+                    spawn_handler(cb, &input_event);
+                    BlockInput::DontBlock
+                }
+                Bind::Block(cb) => {
+                    spawn_handler(cb, &input_event);
+                    BlockInput::Block
+                }
+                Bind::Blockable(cb) => cb(&input_event),
+            };
+            bound_down.insert(key, block);
+            Some(block)
+        }),
+        KeyEvent::Repeat => bound_down.get(&key).copied(),
+        KeyEvent::Release => bound_down.remove(&key),
+    };
+    drop(bound_down);
+
+    match block {
+        Some(block) => {
+            notify_listener(&input_event);
+            block
+        }
+        None => dispatch_keybd_event(key, event, injected, Some(device_name)),
+    }
+}
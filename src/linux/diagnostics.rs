@@ -0,0 +1,227 @@
+use crate::linux::session::{session_type, SessionType};
+use nix::unistd::{access, geteuid, getgroups, AccessFlags, Group};
+use std::{env, fmt, fs::read_dir, path::Path, ptr::null};
+use x11::xlib::{XCloseDisplay, XOpenDisplay};
+
+/// The outcome of a single check, see `diagnostics`.
+#[derive(Debug, Eq, PartialEq, Hash, Copy, Clone)]
+pub enum CheckStatus {
+    Ok,
+    /// Some features won't work.
+    Warning,
+    /// Input can't be read or sent at all.
+    Error,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    /// What was found.
+    pub detail: String,
+    /// What to do about it, if the check didn't pass.
+    pub fix: Option<String>,
+}
+
+/// The report returned by `diagnostics`. Its `Display` output is meant for users.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Diagnostics {
+    pub checks: Vec<Check>,
+}
+
+impl Diagnostics {
+    /// Returns true if no check found an error. Warnings are allowed.
+    pub fn is_ok(&self) -> bool {
+        self.checks
+            .iter()
+            .all(|check| check.status != CheckStatus::Error)
+    }
+}
+
+impl fmt::Display for Diagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for check in &self.checks {
+            let status = match check.status {
+                CheckStatus::Ok => "ok",
+                CheckStatus::Warning => "warning",
+                CheckStatus::Error => "error",
+            };
+            writeln!(f, "[{status}] {}: {}", check.name, check.detail)?;
+            if let Some(fix) = &check.fix {
+                writeln!(f, "    fix: {fix}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Checks whether inputbot has the permissions and services it needs on this system:
+/// access to `/dev/uinput` and `/dev/input/event*`, membership of the `input` group, an X
+/// display, and the session type.
+pub fn diagnostics() -> Diagnostics {
+    Diagnostics {
+        checks: vec![
+            check_uinput(),
+            check_event_devices(),
+            check_input_group(),
+            check_x_display(),
+            check_session(),
+        ],
+    }
+}
+
+fn check(name: &'static str, status: CheckStatus, detail: String, fix: Option<&str>) -> Check {
+    Check {
+        name,
+        status,
+        detail,
+        fix: fix.map(str::to_owned),
+    }
+}
+
+fn check_uinput() -> Check {
+    let path = Path::new("/dev/uinput");
+    if !path.exists() {
+        check(
+            "uinput",
+            CheckStatus::Error,
+            "/dev/uinput doesn't exist, so input can't be sent".to_owned(),
+            Some("load the uinput module with `sudo modprobe uinput`"),
+        )
+    } else if access(path, AccessFlags::R_OK | AccessFlags::W_OK).is_err() {
+        check(
+            "uinput",
+            CheckStatus::Error,
+            "/dev/uinput isn't writable, so input can't be sent".to_owned(),
+            Some(
+                "run as root, or add the udev rule `KERNEL==\"uinput\", GROUP=\"input\", \
+                 MODE=\"0660\"` and join the input group",
+            ),
+        )
+    } else {
+        check(
+            "uinput",
+            CheckStatus::Ok,
+            "/dev/uinput is writable".to_owned(),
+            None,
+        )
+    }
+}
+
+fn check_event_devices() -> Check {
+    let devices: Vec<_> = read_dir("/dev/input")
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with("event"))
+        .map(|entry| entry.path())
+        .collect();
+    let readable = devices
+        .iter()
+        .filter(|path| access(path.as_path(), AccessFlags::R_OK).is_ok())
+        .count();
+    let detail = format!(
+        "{readable} of {} devices in /dev/input are readable",
+        devices.len()
+    );
+    let fix = Some("run as root, or join the input group");
+    if devices.is_empty() {
+        check(
+            "input devices",
+            CheckStatus::Error,
+            "/dev/input has no event devices".to_owned(),
+            Some("make sure /dev is mounted with the host's input devices"),
+        )
+    } else if readable == 0 {
+        check("input devices", CheckStatus::Error, detail, fix)
+    } else if readable < devices.len() {
+        check("input devices", CheckStatus::Warning, detail, fix)
+    } else {
+        check("input devices", CheckStatus::Ok, detail, None)
+    }
+}
+
+fn check_input_group() -> Check {
+    if geteuid().is_root() {
+        return check(
+            "input group",
+            CheckStatus::Ok,
+            "running as root".to_owned(),
+            None,
+        );
+    }
+    match Group::from_name("input") {
+        Ok(Some(group)) if getgroups().is_ok_and(|groups| groups.contains(&group.gid)) => check(
+            "input group",
+            CheckStatus::Ok,
+            "the process is in the input group".to_owned(),
+            None,
+        ),
+        Ok(Some(_)) => check(
+            "input group",
+            CheckStatus::Warning,
+            "the process isn't in the input group".to_owned(),
+            Some("run `sudo usermod -aG input $USER`, then log out and back in"),
+        ),
+        _ => check(
+            "input group",
+            CheckStatus::Warning,
+            "the system has no input group".to_owned(),
+            None,
+        ),
+    }
+}
+
+fn check_x_display() -> Check {
+    match env::var("DISPLAY") {
+        Err(_) if session_type() != SessionType::X11 => check(
+            "X display",
+            CheckStatus::Ok,
+            "not needed outside of X11 sessions".to_owned(),
+            None,
+        ),
+        Err(_) => check(
+            "X display",
+            CheckStatus::Warning,
+            "DISPLAY isn't set, so the cursor is positioned without X".to_owned(),
+            Some("set DISPLAY to the display of the session"),
+        ),
+        Ok(name) => {
+            let display = unsafe { XOpenDisplay(null()) };
+            if display.is_null() {
+                check(
+                    "X display",
+                    CheckStatus::Warning,
+                    format!("can't connect to display {name}"),
+                    Some("check that the display is running and XAUTHORITY is set for root"),
+                )
+            } else {
+                unsafe { XCloseDisplay(display) };
+                check(
+                    "X display",
+                    CheckStatus::Ok,
+                    format!("connected to display {name}"),
+                    None,
+                )
+            }
+        }
+    }
+}
+
+fn check_session() -> Check {
+    match session_type() {
+        SessionType::X11 => check("session", CheckStatus::Ok, "X11 session".to_owned(), None),
+        SessionType::Wayland => check(
+            "session",
+            CheckStatus::Ok,
+            "Wayland session, cursor positioning doesn't use X".to_owned(),
+            None,
+        ),
+        SessionType::Tty => check(
+            "session",
+            CheckStatus::Warning,
+            "no graphical session found".to_owned(),
+            Some("call set_screen_size before positioning the cursor"),
+        ),
+    }
+}
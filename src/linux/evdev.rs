@@ -0,0 +1,150 @@
+use nix::{ioctl_read, ioctl_read_buf, ioctl_write_int};
+use std::os::fd::AsRawFd;
+
+const EV_KEY: usize = 0x01;
+const EV_REL: usize = 0x02;
+const EV_ABS: usize = 0x03;
+const EV_LED: usize = 0x11;
+const LED_MAX: usize = 0x0F;
+const BTN_MISC: usize = 0x100;
+const BTN_LEFT: usize = 0x110;
+const KEY_OK: usize = 0x160;
+const KEY_MAX: usize = 0x2FF;
+
+#[repr(C)]
+#[derive(Default)]
+pub struct InputId {
+    pub bustype: u16,
+    pub vendor: u16,
+    pub product: u16,
+    pub version: u16,
+}
+
+ioctl_read!(eviocgid, b'E', 0x02, InputId);
+ioctl_read_buf!(eviocgname, b'E', 0x06, u8);
+ioctl_read_buf!(eviocgbit_types, b'E', 0x20, u8);
+ioctl_read_buf!(eviocgbit_keys, b'E', 0x20 + EV_KEY, u8);
+ioctl_read_buf!(eviocgkey, b'E', 0x18, u8);
+ioctl_read_buf!(eviocgled, b'E', 0x19, u8);
+ioctl_write_int!(eviocgrab, b'E', 0x90);
+
+fn test_bit(bits: &[u8], bit: usize) -> bool {
+    bits.get(bit / 8)
+        .is_some_and(|byte| byte & (1 << (bit % 8)) != 0)
+}
+
+/// Returns the name the device reports, e.g. "AT Translated Set 2 keyboard".
+pub fn name(fd: &impl AsRawFd) -> String {
+    let mut name = [0u8; 256];
+    match unsafe { eviocgname(fd.as_raw_fd(), &mut name) } {
+        Ok(len) => String::from_utf8_lossy(&name[..len as usize])
+            .trim_end_matches('\0')
+            .to_owned(),
+        Err(_) => String::new(),
+    }
+}
+
+/// Returns the bus type, vendor, product and version ids of the device.
+pub fn id(fd: &impl AsRawFd) -> InputId {
+    let mut id = InputId::default();
+    let _ = unsafe { eviocgid(fd.as_raw_fd(), &mut id) };
+    id
+}
+
+// Returns the event types and keys the device supports.
+fn event_bits(fd: &impl AsRawFd) -> Option<([u8; 4], [u8; KEY_MAX / 8 + 1])> {
+    let mut types = [0u8; 4];
+    let mut keys = [0u8; KEY_MAX / 8 + 1];
+    if unsafe { eviocgbit_types(fd.as_raw_fd(), &mut types) }.is_err()
+        || unsafe { eviocgbit_keys(fd.as_raw_fd(), &mut keys) }.is_err()
+    {
+        return None;
+    }
+    Some((types, keys))
+}
+
+/// Returns true if the device has keyboard keys.
+pub fn has_keys(fd: &impl AsRawFd) -> bool {
+    event_bits(fd).is_some_and(|(types, keys)| {
+        test_bit(&types, EV_KEY) && (1..BTN_MISC).any(|key| test_bit(&keys, key))
+    })
+}
+
+/// Returns true if the device can move the pointer and click.
+pub fn has_pointer(fd: &impl AsRawFd) -> bool {
+    event_bits(fd).is_some_and(|(types, keys)| {
+        test_bit(&types, EV_REL) || (test_bit(&types, EV_ABS) && test_bit(&keys, BTN_LEFT))
+    })
+}
+
+/// Returns true if the device has keyboard keys and no pointer axes.
+///
+/// Devices with axes are left alone, so that grabbing them doesn't take the mouse
+/// away from the system.
+pub fn is_keyboard(fd: &impl AsRawFd) -> bool {
+    event_bits(fd).is_some_and(|(types, _)| !test_bit(&types, EV_REL) && !test_bit(&types, EV_ABS))
+        && has_keys(fd)
+}
+
+pub const LED_NUML: usize = 0x00;
+pub const LED_CAPSL: usize = 0x01;
+pub const LED_SCROLLL: usize = 0x02;
+pub const LED_COMPOSE: usize = 0x03;
+pub const LED_KANA: usize = 0x04;
+
+/// Returns the LEDs which are on for any device in /dev/input, as a bitmask of the LED
+/// codes. The kernel keeps the lock LEDs of all keyboards in sync.
+pub fn leds() -> u16 {
+    let Ok(entries) = std::fs::read_dir("/dev/input") else {
+        return 0;
+    };
+    entries
+        .filter_map(|entry| std::fs::File::open(entry.ok()?.path()).ok())
+        .filter(|file| event_bits(file).is_some_and(|(types, _)| test_bit(&types, EV_LED)))
+        .fold(0, |on, file| {
+            let mut leds = [0u8; LED_MAX / 8 + 1];
+            match unsafe { eviocgled(file.as_raw_fd(), &mut leds) } {
+                Ok(_) => on | u16::from_le_bytes(leds),
+                Err(_) => on,
+            }
+        })
+}
+
+/// Returns true for the codes of mouse, joystick and other buttons, which share the code
+/// space with keys.
+pub fn is_button(code: u32) -> bool {
+    (BTN_MISC..KEY_OK).contains(&(code as usize))
+}
+
+/// Returns the codes of the keys and buttons which are down on any device in /dev/input.
+pub fn keys_down() -> Vec<u32> {
+    let Ok(entries) = std::fs::read_dir("/dev/input") else {
+        return Vec::new();
+    };
+    let down = entries
+        .filter_map(|entry| std::fs::File::open(entry.ok()?.path()).ok())
+        .filter(|file| event_bits(file).is_some_and(|(types, _)| test_bit(&types, EV_KEY)))
+        .fold([0u8; KEY_MAX / 8 + 1], |mut down, file| {
+            let mut keys = [0u8; KEY_MAX / 8 + 1];
+            if unsafe { eviocgkey(file.as_raw_fd(), &mut keys) }.is_ok() {
+                down.iter_mut()
+                    .zip(keys)
+                    .for_each(|(down, keys)| *down |= keys);
+            }
+            down
+        });
+    (0..=KEY_MAX)
+        .filter(|&key| test_bit(&down, key))
+        .map(|key| key as u32)
+        .collect()
+}
+
+/// Grabs the device, so that its events are only delivered to this file descriptor.
+pub fn grab(fd: &impl AsRawFd) -> nix::Result<()> {
+    unsafe { eviocgrab(fd.as_raw_fd(), 1) }.map(drop)
+}
+
+/// Gives a grabbed device back to the system.
+pub fn ungrab(fd: &impl AsRawFd) -> nix::Result<()> {
+    unsafe { eviocgrab(fd.as_raw_fd(), 0) }.map(drop)
+}
@@ -1,11 +1,13 @@
-use crate::{common::*, linux::inputs::*, public::*};
+use crate::{common::*, linux::inputs::*, physical::*, public::*};
 use input::{
     event::{
+        device::DeviceEvent,
         keyboard::{
             KeyState, {KeyboardEvent, KeyboardEventTrait},
         },
         pointer::{ButtonState, PointerEvent::*},
         Event::{self, *},
+        EventTrait,
     },
     Libinput, LibinputInterface,
 };
@@ -16,35 +18,94 @@ use nix::{
 };
 use once_cell::sync::Lazy;
 use std::{
-    mem::MaybeUninit,
-    os::fd::{FromRawFd, IntoRawFd, OwnedFd},
-    path::Path,
-    ptr::null,
+    os::fd::{AsRawFd, BorrowedFd, FromRawFd, IntoRawFd, OwnedFd, RawFd},
+    path::{Path, PathBuf},
+    ptr::{null, null_mut},
     sync::atomic::{AtomicBool, Ordering},
     sync::Mutex,
-    thread::sleep,
-    time::Duration,
+    time::{Duration, Instant},
 };
 use uinput::event::{
+    absolute::{self, Absolute},
     controller::{Controller, Mouse},
-    relative::Position,
+    relative::{Position, Wheel},
     Event as UinputEvent,
 };
 use x11::xlib::*;
 
+#[cfg(feature = "daemon")]
+use crate::daemon::DaemonRequest;
+
+mod devices;
+mod diagnostics;
+mod evdev;
 mod inputs;
+mod session;
+
+pub use self::{devices::*, diagnostics::*, session::*};
+
+// Name of the uinput device, which is also how its events are recognized as injected.
+const FAKE_DEVICE_NAME: &str = "inputbot";
+const ABSOLUTE_DEVICE_NAME: &str = "inputbot absolute";
+
+fn is_own_device(name: &str) -> bool {
+    name == FAKE_DEVICE_NAME || name == ABSOLUTE_DEVICE_NAME
+}
+
+// Makes the enclosing function send the request to inputbotd instead, when connected to it
+// (see `client`), and return the answer of a `query`.
+macro_rules! forward_to_daemon {
+    ($request:expr) => {
+        #[cfg(feature = "daemon")]
+        if crate::client::forward($request).is_some() {
+            return;
+        }
+    };
+    (query $request:expr) => {
+        #[cfg(feature = "daemon")]
+        if let Some(answer) = crate::client::forward($request) {
+            return answer.unwrap_or_default();
+        }
+    };
+}
+
+// Where libinput gets its devices from.
+#[derive(Clone)]
+enum DeviceSource {
+    Udev(String),
+    Paths(Vec<PathBuf>),
+}
 
 type ButtonStatesMap = HashMap<MouseButton, bool>;
 type KeyStatesMap = HashMap<KeybdKey, bool>;
+type RepeatingKey = (KeybdKey, Instant, bool, String);
 
 static BUTTON_STATES: Lazy<Mutex<ButtonStatesMap>> =
     Lazy::new(|| Mutex::new(ButtonStatesMap::new()));
 static KEY_STATES: Lazy<Mutex<KeyStatesMap>> = Lazy::new(|| Mutex::new(KeyStatesMap::new()));
+// The lock states while `handle_input_events` runs, read from the LEDs when it starts and
+// then toggled by the lock keys, so that events don't have to ask the keyboards.
+static LOCK_STATE: Lazy<Mutex<LockState>> = Lazy::new(|| Mutex::new(LockState::default()));
+static KEY_REPEAT: Lazy<Mutex<(Duration, Duration)>> =
+    Lazy::new(|| Mutex::new((Duration::from_millis(600), Duration::from_millis(40))));
+// The key which autorepeats, when it repeats next, whether its press was injected, and the
+// name of the device it was pressed on.
+static REPEATING_KEY: Lazy<Mutex<Option<RepeatingKey>>> = Lazy::new(|| Mutex::new(None));
+static DEVICE_SOURCE: Lazy<Mutex<DeviceSource>> =
+    Lazy::new(|| Mutex::new(DeviceSource::Udev(String::from("seat0"))));
+static GRAB_KEYBOARDS: AtomicBool = AtomicBool::new(false);
+// The descriptors of the grabbed keyboards, by sysname.
+static GRABBED_DEVICES: Lazy<Mutex<HashMap<String, RawFd>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+// Key events forwarded from grabbed keyboards, which will come back from the fake device
+// and must not be handled twice.
+static FORWARDED_KEYS: Lazy<Mutex<HashMap<(u32, bool), usize>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
 static FAKE_DEVICE: Lazy<Mutex<uinput::Device>> = Lazy::new(|| {
     Mutex::new(
         uinput::default()
-            .unwrap()
-            .name("inputbot")
+            .expect("can't open /dev/uinput, see inputbot::diagnostics()")
+            .name(FAKE_DEVICE_NAME)
             .unwrap()
             .event(uinput::event::Keyboard::All)
             .unwrap()
@@ -68,11 +129,23 @@ static FAKE_DEVICE: Lazy<Mutex<uinput::Device>> = Lazy::new(|| {
             .unwrap()
             .event(Position::Y)
             .unwrap()
+            .event(Wheel::Vertical)
+            .unwrap()
+            .event(Wheel::Horizontal)
+            .unwrap()
             .create()
             .unwrap(),
     )
 });
+// Positions the cursor outside of X11 sessions. libinput maps the range of an absolute
+// pointer onto the screen, so a range of the screen size in pixels maps 1:1. Created on
+// the first move, and again on the next one if that fails.
+static ABSOLUTE_DEVICE: Lazy<Mutex<Option<uinput::Device>>> = Lazy::new(|| Mutex::new(None));
+// Null when there is no X display, or when it's XWayland and of no use for global input.
 static SEND_DISPLAY: Lazy<AtomicPtr<Display>> = Lazy::new(|| {
+    if session_type() != SessionType::X11 {
+        return AtomicPtr::new(null_mut());
+    }
     unsafe { XInitThreads() };
     AtomicPtr::new(unsafe { XOpenDisplay(null()) })
 });
@@ -82,72 +155,245 @@ static SEND_DISPLAY: Lazy<AtomicPtr<Display>> = Lazy::new(|| {
 /// Can be called before using the fake device to prevent it from
 /// building when you first try to use it.
 pub fn init_device() {
+    #[cfg(feature = "daemon")]
+    if crate::client::is_connected() {
+        return;
+    }
     drop(FAKE_DEVICE.lock().unwrap());
 }
 
+/// Sets how long a key has to be held before it starts repeating, and the interval
+/// between repeats.
+///
+/// libinput doesn't report keyboard autorepeat, so repeat events are generated in
+/// software for binds that enable `BindOptions::repeat`. Defaults to 600ms and 40ms.
+pub fn set_key_repeat(delay: Duration, interval: Duration) {
+    *KEY_REPEAT.lock().unwrap() = (delay, interval);
+}
+
+/// Sets the seat whose devices `handle_input_events` reads. Defaults to "seat0".
+pub fn set_seat(seat: &str) {
+    *DEVICE_SOURCE.lock().unwrap() = DeviceSource::Udev(seat.to_owned());
+}
+
+/// Makes `handle_input_events` read only the given devices, e.g. "/dev/input/event3",
+/// instead of finding them through udev. This works without udev, e.g. in containers, but
+/// devices which are plugged in later aren't picked up. `set_seat` switches back to udev.
+///
+/// Paths which can't be read are reported on stderr when `handle_input_events` starts.
+pub fn set_device_paths<I: IntoIterator<Item = P>, P: Into<PathBuf>>(paths: I) {
+    *DEVICE_SOURCE.lock().unwrap() =
+        DeviceSource::Paths(paths.into_iter().map(Into::into).collect());
+}
+
+/// Grabs keyboards when `handle_input_events` starts, so that block binds, dual-role
+/// keys and other remapping can hide key events from the rest of the system.
+///
+/// Events which aren't blocked are passed on through the fake device. Devices with pointer
+/// axes are never grabbed, so mouse buttons can't be blocked.
+pub fn set_grab_keyboards(grab: bool) {
+    GRAB_KEYBOARDS.store(grab, Ordering::Relaxed);
+}
+
+// Ungrabs the grabbed keyboards and stops grabbing new ones, for when their events can't
+// be passed on anymore.
+pub(crate) fn release_grabs() {
+    GRAB_KEYBOARDS.store(false, Ordering::Relaxed);
+    for (_, fd) in GRABBED_DEVICES.lock().unwrap().drain() {
+        let _ = evdev::ungrab(&unsafe { BorrowedFd::borrow_raw(fd) });
+    }
+}
+
 impl KeybdKey {
     /// Returns true if a given `KeybdKey` is currently pressed (in the down position).
+    ///
+    /// Without `handle_input_events` running, this asks the keyboards, which needs read
+    /// access to them.
     pub fn is_pressed(self) -> bool {
-        *KEY_STATES.lock().unwrap().entry(self).or_insert(false)
+        if HANDLE_EVENTS.load(Ordering::Relaxed) {
+            *KEY_STATES.lock().unwrap().entry(self).or_insert(false)
+        } else {
+            forward_to_daemon!(query DaemonRequest::IsPressed {
+                input: Input::Keybd(self)
+            });
+            self.physical_key()
+                .is_some_and(|PhysicalKey(code)| evdev::keys_down().contains(&(code as u32)))
+        }
     }
 
     /// Presses a given `KeybdKey`. Note: this means the key will remain in the down
     /// position. You must manually call release to create a full 'press'.
     pub fn press(self) {
-        let mut device = FAKE_DEVICE.lock().unwrap();
+        forward_to_daemon!(DaemonRequest::Press {
+            input: Input::Keybd(self)
+        });
+        if let Some(PhysicalKey(code)) = self.physical_key() {
+            let mut device = FAKE_DEVICE.lock().unwrap();
 
-        device.write(0x01, key_to_scan_code(self), 1).unwrap();
-        device.synchronize().unwrap();
+            device.write(0x01, code as i32, 1).unwrap();
+            device.synchronize().unwrap();
+        }
     }
 
     /// Releases a given `KeybdKey`. This means the key would be in the up position.
     pub fn release(self) {
-        let mut device = FAKE_DEVICE.lock().unwrap();
+        forward_to_daemon!(DaemonRequest::Release {
+            input: Input::Keybd(self)
+        });
+        if let Some(PhysicalKey(code)) = self.physical_key() {
+            let mut device = FAKE_DEVICE.lock().unwrap();
 
-        device.write(0x01, key_to_scan_code(self), 0).unwrap();
-        device.synchronize().unwrap();
+            device.write(0x01, code as i32, 0).unwrap();
+            device.synchronize().unwrap();
+        }
     }
 
     /// Returns true if a keyboard key which supports toggling (ScrollLock, NumLock,
     /// CapsLock) is on.
+    ///
+    /// This reads the LEDs of the keyboards, which needs read access to them, see
+    /// `lock_state`.
     pub fn is_toggled(self) -> bool {
-        if let Some(key) = match self {
-            KeybdKey::ScrollLockKey => Some(4),
-            KeybdKey::NumLockKey => Some(2),
-            KeybdKey::CapsLockKey => Some(1),
-            _ => None,
-        } {
-            let mut state: XKeyboardState = unsafe { MaybeUninit::zeroed().assume_init() };
-            SEND_DISPLAY.with(|display| unsafe {
-                XGetKeyboardControl(display, &mut state);
-            });
-            state.led_mask & key != 0
-        } else {
-            false
+        if !HANDLE_EVENTS.load(Ordering::Relaxed) {
+            forward_to_daemon!(query DaemonRequest::IsToggled { key: self });
         }
+        let locks = lock_state();
+        match self {
+            KeybdKey::ScrollLockKey => locks.scroll_lock,
+            KeybdKey::NumLockKey => locks.num_lock,
+            KeybdKey::CapsLockKey => locks.caps_lock,
+            _ => false,
+        }
+    }
+}
+
+/// Returns the keys which are currently pressed, see `KeybdKey::is_pressed`.
+pub fn pressed_keys() -> Vec<KeybdKey> {
+    if HANDLE_EVENTS.load(Ordering::Relaxed) {
+        pressed(&KEY_STATES.lock().unwrap())
+    } else {
+        evdev::keys_down()
+            .into_iter()
+            .filter(|&code| !evdev::is_button(code))
+            .map(|code| PhysicalKey(code as u16).keybd_key())
+            .collect()
+    }
+}
+
+/// Returns the mouse buttons which are currently pressed, see `MouseButton::is_pressed`.
+pub fn pressed_buttons() -> Vec<MouseButton> {
+    if HANDLE_EVENTS.load(Ordering::Relaxed) {
+        pressed(&BUTTON_STATES.lock().unwrap())
+    } else {
+        evdev::keys_down()
+            .into_iter()
+            .filter_map(button_code_to_button)
+            .collect()
+    }
+}
+
+fn pressed<K: Copy>(states: &HashMap<K, bool>) -> Vec<K> {
+    states
+        .iter()
+        .filter(|(_, &down)| down)
+        .map(|(&key, _)| key)
+        .collect()
+}
+
+// Starts the state tables off with the keys and buttons which are already down, since
+// libinput only reports changes.
+fn seed_input_states() {
+    let mut key_states = KEY_STATES.lock().unwrap();
+    let mut button_states = BUTTON_STATES.lock().unwrap();
+    key_states.clear();
+    button_states.clear();
+    for code in evdev::keys_down() {
+        if !evdev::is_button(code) {
+            key_states.insert(PhysicalKey(code as u16).keybd_key(), true);
+        } else if let Some(button) = button_code_to_button(code) {
+            button_states.insert(button, true);
+        }
+    }
+    *LOCK_STATE.lock().unwrap() = lock_state();
+}
+
+// Keeps the lock states in step with a press of a lock key which reached the system.
+fn toggle_lock(key: KeybdKey) {
+    let mut locks = LOCK_STATE.lock().unwrap();
+    match key {
+        KeybdKey::CapsLockKey => locks.caps_lock ^= true,
+        KeybdKey::NumLockKey => locks.num_lock ^= true,
+        KeybdKey::ScrollLockKey => locks.scroll_lock ^= true,
+        _ => {}
+    }
+}
+
+/// Returns which lock states are on, read from the LEDs of the keyboards.
+///
+/// While `handle_input_events` runs, they are read once when it starts and then follow the
+/// presses of the lock keys, so that input events don't have to ask the keyboards.
+pub fn lock_state() -> LockState {
+    if HANDLE_EVENTS.load(Ordering::Relaxed) {
+        return *LOCK_STATE.lock().unwrap();
+    }
+    // The daemon only tells the lock keys apart.
+    #[cfg(feature = "daemon")]
+    if crate::client::is_connected() {
+        return LockState {
+            caps_lock: KeybdKey::CapsLockKey.is_toggled(),
+            num_lock: KeybdKey::NumLockKey.is_toggled(),
+            scroll_lock: KeybdKey::ScrollLockKey.is_toggled(),
+            ..LockState::default()
+        };
+    }
+    let leds = evdev::leds();
+    let on = |led: usize| leds & 1 << led != 0;
+    LockState {
+        caps_lock: on(evdev::LED_CAPSL),
+        num_lock: on(evdev::LED_NUML),
+        scroll_lock: on(evdev::LED_SCROLLL),
+        compose: on(evdev::LED_COMPOSE),
+        kana: on(evdev::LED_KANA),
     }
 }
 
 impl MouseButton {
     /// Returns true if a given `MouseButton` is currently pressed (in the down position).
+    ///
+    /// Without `handle_input_events` running, this asks the mice, which needs read access
+    /// to them.
     pub fn is_pressed(self) -> bool {
-        *BUTTON_STATES.lock().unwrap().entry(self).or_insert(false)
+        if HANDLE_EVENTS.load(Ordering::Relaxed) {
+            *BUTTON_STATES.lock().unwrap().entry(self).or_insert(false)
+        } else {
+            forward_to_daemon!(query DaemonRequest::IsPressed {
+                input: Input::Mouse(self)
+            });
+            pressed_buttons().contains(&self)
+        }
     }
 
     /// Presses a given `MouseButton`. Note: this means the button will remain in the down
     /// position. You must manually call release to create a full 'click'.
     pub fn press(self) {
+        forward_to_daemon!(DaemonRequest::Press {
+            input: Input::Mouse(self)
+        });
+        // Converted first, so that buttons which can't be sent don't poison the device.
+        let button = Controller::Mouse(Mouse::from(self));
         let mut device = FAKE_DEVICE.lock().unwrap();
-        device.press(&Controller::Mouse(Mouse::from(self))).unwrap();
+        device.press(&button).unwrap();
         device.synchronize().unwrap();
     }
 
     /// Releases a given `MouseButton`. This means the button would be in the up position.
     pub fn release(self) {
+        forward_to_daemon!(DaemonRequest::Release {
+            input: Input::Mouse(self)
+        });
+        let button = Controller::Mouse(Mouse::from(self));
         let mut device = FAKE_DEVICE.lock().unwrap();
-        device
-            .release(&Controller::Mouse(Mouse::from(self)))
-            .unwrap();
+        device.release(&button).unwrap();
         device.synchronize().unwrap();
     }
 }
@@ -155,6 +401,11 @@ impl MouseButton {
 impl MouseCursor {
     /// Moves the mouse relative to its current position by a given amount of pixels.
     pub fn move_rel(x: i32, y: i32) {
+        forward_to_daemon!(DaemonRequest::Move {
+            x,
+            y,
+            relative: true
+        });
         let mut device = FAKE_DEVICE.lock().unwrap();
 
         device.position(&Position::X, x).unwrap();
@@ -167,146 +418,401 @@ impl MouseCursor {
     }
 
     /// Moves the mouse to a given position based on absolute coordinates. The top left
-    /// corner of the screen is (0, 0).
+    /// corner of the screen is (0, 0). Does nothing if the cursor can't be positioned,
+    /// see `try_move_abs`.
+    ///
+    /// Outside of X11 sessions this goes through an absolute pointer device, which needs
+    /// the screen size (see `set_screen_size`) and covers the first screen only.
     pub fn move_abs(x: i32, y: i32) {
-        let mut device = FAKE_DEVICE.lock().unwrap();
+        let _ = Self::try_move_abs(x, y);
+    }
 
-        SEND_DISPLAY.with(|display| unsafe {
-            XWarpPointer(
-                display,
-                0,
-                XRootWindow(display, XDefaultScreen(display)),
-                0,
-                0,
-                0,
-                0,
+    /// Moves the mouse like `move_abs`, or returns why it can't.
+    pub fn try_move_abs(x: i32, y: i32) -> Result<(), CursorError> {
+        #[cfg(feature = "daemon")]
+        if crate::client::is_connected() {
+            return crate::client::request(DaemonRequest::Move {
                 x,
                 y,
-            );
-        });
-        device.synchronize().unwrap();
+                relative: false,
+            })
+            .map(drop)
+            .map_err(|err| CursorError::Other(err.to_string()));
+        }
+        if SEND_DISPLAY
+            .with(|display| unsafe {
+                XWarpPointer(
+                    display,
+                    0,
+                    XRootWindow(display, XDefaultScreen(display)),
+                    0,
+                    0,
+                    0,
+                    0,
+                    x,
+                    y,
+                );
+            })
+            .is_some()
+        {
+            return Ok(());
+        }
+
+        let mut device = ABSOLUTE_DEVICE.lock().unwrap();
+        if device.is_none() {
+            *device = Some(absolute_device()?);
+        }
+        let device = device.as_mut().unwrap();
+        device
+            .position(&Absolute::Position(absolute::Position::X), x)
+            .and_then(|()| device.position(&Absolute::Position(absolute::Position::Y), y))
+            .and_then(|()| device.synchronize())
+            .map_err(|err| CursorError::Other(err.to_string()))
     }
 }
 
 impl MouseWheel {
-    /// Scrolls the mouse wheel vertically by a given amount.
+    /// Scrolls the mouse wheel vertically by a given amount of notches, down if positive.
     pub fn scroll_ver(y: i32) {
-        if y < 0 {
-            MouseButton::OtherButton(4).press();
-            MouseButton::OtherButton(4).release();
-        } else {
-            MouseButton::OtherButton(5).press();
-            MouseButton::OtherButton(5).release();
-        }
+        forward_to_daemon!(DaemonRequest::Scroll {
+            notches: y,
+            horizontal: false
+        });
+        scroll(y, false);
     }
 
-    /// Scrolls the mouse wheel horizontally by a given amount.
+    /// Scrolls the mouse wheel horizontally by a given amount of notches, right if
+    /// positive.
     pub fn scroll_hor(x: i32) {
-        if x < 0 {
-            MouseButton::OtherButton(6).press();
-            MouseButton::OtherButton(6).release();
-        } else {
-            MouseButton::OtherButton(7).press();
-            MouseButton::OtherButton(7).release();
-        }
+        forward_to_daemon!(DaemonRequest::Scroll {
+            notches: x,
+            horizontal: true
+        });
+        scroll(x, true);
     }
 }
 
-struct LibinputInterfaceRaw;
+fn scroll(notches: i32, horizontal: bool) {
+    let (wheel, value) = wheel_event(notches, horizontal);
+    let mut device = FAKE_DEVICE.lock().unwrap();
+    device.position(&wheel, value).unwrap();
+    device.synchronize().unwrap();
+}
 
-impl LibinputInterfaceRaw {
-    fn seat(&self) -> String {
-        String::from("seat0")
+// The wheel moves up and right for positive values, while `MouseWheel` scrolls down and
+// right for positive amounts.
+fn wheel_event(notches: i32, horizontal: bool) -> (Wheel, i32) {
+    if horizontal {
+        (Wheel::Horizontal, notches)
+    } else {
+        (Wheel::Vertical, -notches)
     }
 }
 
+fn absolute_device() -> Result<uinput::Device, CursorError> {
+    let (width, height) = screen_size().ok_or(CursorError::UnknownScreenSize)?;
+    let device = uinput::default()
+        .and_then(|builder| builder.name(ABSOLUTE_DEVICE_NAME))
+        .and_then(|builder| builder.event(Absolute::Position(absolute::Position::X)))
+        .map(|builder| builder.max(width - 1))
+        .and_then(|builder| builder.event(Absolute::Position(absolute::Position::Y)))
+        .map(|builder| builder.max(height - 1))
+        .and_then(|builder| builder.event(UinputEvent::Controller(Controller::Mouse(Mouse::Left))))
+        .and_then(|builder| builder.create())
+        .map_err(|err| CursorError::Other(err.to_string()))?;
+    // Give the compositor time to pick up the new device, or the first move is lost.
+    sleep(Duration::from_millis(200));
+    Ok(device)
+}
+
+struct LibinputInterfaceRaw;
+
 impl LibinputInterface for LibinputInterfaceRaw {
     fn open_restricted(&mut self, path: &Path, flags: i32) -> std::result::Result<OwnedFd, i32> {
-        if let Ok(fd) = open(path, OFlag::from_bits_truncate(flags), Mode::empty()) {
-            Ok(unsafe { OwnedFd::from_raw_fd(fd) })
-        } else {
-            Err(1)
+        match open(path, OFlag::from_bits_truncate(flags), Mode::empty()) {
+            Ok(fd) => {
+                let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+                if GRAB_KEYBOARDS.load(Ordering::Relaxed)
+                    && evdev::is_keyboard(&fd)
+                    && evdev::name(&fd) != FAKE_DEVICE_NAME
+                    && evdev::grab(&fd).is_ok()
+                {
+                    if let Some(sysname) = path.file_name() {
+                        GRABBED_DEVICES
+                            .lock()
+                            .unwrap()
+                            .insert(sysname.to_string_lossy().into_owned(), fd.as_raw_fd());
+                    }
+                }
+                Ok(fd)
+            }
+            // libinput logs which device couldn't be opened, and why.
+            Err(errno) => Err(errno as i32),
         }
     }
 
     fn close_restricted(&mut self, fd: OwnedFd) {
+        GRABBED_DEVICES
+            .lock()
+            .unwrap()
+            .retain(|_, grabbed| *grabbed != fd.as_raw_fd());
         let _ = close(fd.into_raw_fd());
     }
 }
 
 /// Starts listening for bound input events (otionally stopping when binds are removed).
+///
+/// When connected to inputbotd (see `client`), the events come from the daemon instead.
 pub fn handle_input_events(auto_stop: bool) {
-    let mut libinput_context = Libinput::new_with_udev(LibinputInterfaceRaw);
-    libinput_context
-        .udev_assign_seat(&LibinputInterfaceRaw.seat())
-        .unwrap();
+    #[cfg(feature = "daemon")]
+    if crate::client::is_connected() {
+        return handle_daemon_events(auto_stop);
+    }
+    let source = DEVICE_SOURCE.lock().unwrap().clone();
+    let mut libinput_context = match source {
+        DeviceSource::Udev(seat) => {
+            let mut libinput_context = Libinput::new_with_udev(LibinputInterfaceRaw);
+            libinput_context.udev_assign_seat(&seat).unwrap();
+            libinput_context
+        }
+        DeviceSource::Paths(paths) => {
+            let mut libinput_context = Libinput::new_from_path(LibinputInterfaceRaw);
+            // The fake device is read as well, to recognize injected and forwarded events.
+            let fake_device = fake_device_path();
+            if fake_device.is_none() {
+                eprintln!("inputbot: can't find the fake device, injected input looks real");
+            }
+            for path in paths.iter().chain(&fake_device) {
+                if libinput_context
+                    .path_add_device(&path.to_string_lossy())
+                    .is_none()
+                {
+                    eprintln!("inputbot: can't read {}", path.display());
+                }
+            }
+            libinput_context
+        }
+    };
 
+    seed_input_states();
     HANDLE_EVENTS.store(true, Ordering::Relaxed);
     while should_continue(auto_stop) {
+        // Wake up at least every 10ms to generate key repeats and check whether to stop.
+        let mut poll_fd = libc::pollfd {
+            fd: libinput_context.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        unsafe { libc::poll(&mut poll_fd, 1, 10) };
         libinput_context.dispatch().unwrap();
 
         for event in libinput_context.by_ref() {
             handle_input_event(event);
         }
+        generate_key_repeat();
+    }
+}
 
+// Creates the fake device and returns its path. udev creates the device node a little
+// after the device, so it is looked for until it shows up.
+fn fake_device_path() -> Option<PathBuf> {
+    init_device();
+    for _ in 0..100 {
+        if let Some(device) = devices()
+            .into_iter()
+            .find(|device| device.name == FAKE_DEVICE_NAME)
+        {
+            return Some(Path::new("/dev/input").join(device.sysname));
+        }
         sleep(Duration::from_millis(10));
     }
+    None
+}
+
+// Runs the binds on the events of inputbotd. They have already reached the rest of the
+// system, so nothing can be blocked.
+#[cfg(feature = "daemon")]
+fn handle_daemon_events(auto_stop: bool) {
+    use std::sync::mpsc::RecvTimeoutError;
+
+    let events = crate::client::subscribe().unwrap_or_else(|err| panic!("{err}"));
+    KEY_STATES.lock().unwrap().clear();
+    BUTTON_STATES.lock().unwrap().clear();
+    *LOCK_STATE.lock().unwrap() = lock_state();
+    HANDLE_EVENTS.store(true, Ordering::Relaxed);
+    while should_continue(auto_stop) {
+        let event = match events.recv_timeout(Duration::from_millis(10)) {
+            Ok(event) => event,
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => panic!("lost the connection to inputbotd"),
+        };
+        let down = event.event != KeyEvent::Release;
+        match event.input {
+            Input::Keybd(key) => {
+                KEY_STATES.lock().unwrap().insert(key, down);
+                if event.event == KeyEvent::Press {
+                    toggle_lock(key);
+                }
+            }
+            Input::Mouse(button) => {
+                BUTTON_STATES.lock().unwrap().insert(button, down);
+            }
+        }
+        dispatch_input_event(event);
+    }
+    crate::client::unsubscribe();
 }
 
 fn handle_input_event(event: Event) {
     match event {
         Keyboard(KeyboardEvent::Key(keyboard_key_event)) => {
             let key = keyboard_key_event.key();
-            if let Some(keybd_key) = scan_code_to_key(key) {
-                if keyboard_key_event.key_state() == KeyState::Pressed {
-                    KEY_STATES.lock().unwrap().insert(keybd_key, true);
+            let pressed = keyboard_key_event.key_state() == KeyState::Pressed;
+            let device = keyboard_key_event.device();
+            let injected = device.name() == FAKE_DEVICE_NAME;
+            if injected && take_forwarded_key(key, pressed) {
+                return;
+            }
 
-                    if let Some(Bind::Normal(cb)) = KEYBD_BINDS.lock().unwrap().get(&keybd_key) {
-                        let cb = Arc::clone(cb);
-                        spawn(move || cb());
-                    }
-                } else {
-                    KEY_STATES.lock().unwrap().insert(keybd_key, false);
+            // Keys without a variant of their own are delivered as `OtherKey`.
+            let keybd_key = PhysicalKey(key as u16).keybd_key();
+            let block = if pressed {
+                KEY_STATES.lock().unwrap().insert(keybd_key, true);
+                *REPEATING_KEY.lock().unwrap() = Some((
+                    keybd_key,
+                    Instant::now() + KEY_REPEAT.lock().unwrap().0,
+                    injected,
+                    device.name().to_owned(),
+                ));
+
+                dispatch_device_keybd_event(
+                    Some(&device),
+                    device.name(),
+                    keybd_key,
+                    KeyEvent::Press,
+                    injected,
+                )
+            } else {
+                KEY_STATES.lock().unwrap().insert(keybd_key, false);
+                let mut repeating_key = REPEATING_KEY.lock().unwrap();
+                if matches!(*repeating_key, Some((k, ..)) if k == keybd_key) {
+                    *repeating_key = None;
                 }
+                drop(repeating_key);
+
+                dispatch_device_keybd_event(
+                    Some(&device),
+                    device.name(),
+                    keybd_key,
+                    KeyEvent::Release,
+                    injected,
+                )
+            };
+
+            // Blocked events of grabbed keyboards don't reach the system.
+            let grabbed = GRABBED_DEVICES
+                .lock()
+                .unwrap()
+                .contains_key(device.sysname());
+            if pressed && (block == BlockInput::DontBlock || !grabbed) {
+                toggle_lock(keybd_key);
+            }
+            if block == BlockInput::DontBlock && grabbed {
+                forward_key(key, pressed);
             }
         }
         Pointer(Button(button_event)) => {
             let button = button_event.button();
-            if let Some(mouse_button) = match button {
-                272 => Some(MouseButton::LeftButton),
-                273 => Some(MouseButton::RightButton),
-                274 => Some(MouseButton::MiddleButton),
-                275 => Some(MouseButton::X1Button),
-                276 => Some(MouseButton::X2Button),
-                _ => None,
-            } {
+            if let Some(mouse_button) = button_code_to_button(button) {
+                let device = button_event.device();
+                let injected = is_own_device(device.name());
+                let name = Some(device.name());
                 if button_event.button_state() == ButtonState::Pressed {
                     BUTTON_STATES.lock().unwrap().insert(mouse_button, true);
-                    if let Some(Bind::Normal(cb)) = MOUSE_BINDS.lock().unwrap().get(&mouse_button) {
-                        let cb = Arc::clone(cb);
-                        spawn(move || cb());
-                    };
+                    dispatch_mouse_event(mouse_button, KeyEvent::Press, injected, name);
                 } else {
                     BUTTON_STATES.lock().unwrap().insert(mouse_button, false);
+                    dispatch_mouse_event(mouse_button, KeyEvent::Release, injected, name);
                 }
             }
         }
+        Device(DeviceEvent::Added(device_event)) => {
+            let device = device_event.device();
+            if !is_own_device(device.name()) {
+                notify_hotplug_listener(HotplugEvent::Added(InputDevice::from(&device)));
+            }
+        }
+        Device(DeviceEvent::Removed(device_event)) => {
+            let device = device_event.device();
+            GRABBED_DEVICES.lock().unwrap().remove(device.sysname());
+            if !is_own_device(device.name()) {
+                notify_hotplug_listener(HotplugEvent::Removed(InputDevice::from(&device)));
+            }
+        }
         _ => {}
     }
 }
 
+// Like a hardware keyboard, only the most recently pressed key repeats.
+fn generate_key_repeat() {
+    let mut repeating_key = REPEATING_KEY.lock().unwrap();
+    if let Some((key, next_repeat, injected, device_name)) = repeating_key.as_mut() {
+        if Instant::now() >= *next_repeat {
+            *next_repeat += KEY_REPEAT.lock().unwrap().1;
+            let (key, injected, device_name) = (*key, *injected, device_name.clone());
+            drop(repeating_key);
+
+            dispatch_device_keybd_event(None, &device_name, key, KeyEvent::Repeat, injected);
+        }
+    }
+}
+
+// Passes an event of a grabbed keyboard on to the system. This is queued like other
+// injected input, so that it can't overtake events which were injected before it.
+fn forward_key(key: u32, pressed: bool) {
+    *FORWARDED_KEYS
+        .lock()
+        .unwrap()
+        .entry((key, pressed))
+        .or_insert(0) += 1;
+    queue_injection(move || {
+        let mut device = FAKE_DEVICE.lock().unwrap();
+        device.write(0x01, key as i32, pressed as i32).unwrap();
+        device.synchronize().unwrap();
+    });
+}
+
+fn take_forwarded_key(key: u32, pressed: bool) -> bool {
+    let mut forwarded_keys = FORWARDED_KEYS.lock().unwrap();
+    match forwarded_keys.get_mut(&(key, pressed)) {
+        Some(count) if *count > 1 => {
+            *count -= 1;
+            true
+        }
+        Some(_) => {
+            forwarded_keys.remove(&(key, pressed));
+            true
+        }
+        None => false,
+    }
+}
+
 trait DisplayAcquirable {
-    fn with<F, Z>(&self, cb: F) -> Z
+    /// Calls `cb` with the display, or returns None if there is none.
+    fn with<F, Z>(&self, cb: F) -> Option<Z>
     where
         F: FnOnce(*mut Display) -> Z;
 }
 
 impl DisplayAcquirable for AtomicPtr<Display> {
-    fn with<F, Z>(&self, cb: F) -> Z
+    fn with<F, Z>(&self, cb: F) -> Option<Z>
     where
         F: FnOnce(*mut Display) -> Z,
     {
         let display = self.load(Ordering::Relaxed);
+        if display.is_null() {
+            return None;
+        }
         unsafe {
             XLockDisplay(display);
         };
@@ -315,6 +821,25 @@ impl DisplayAcquirable for AtomicPtr<Display> {
             XFlush(display);
             XUnlockDisplay(display);
         };
-        cb_result
+        Some(cb_result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wheels_scroll_by_notches() {
+        assert_eq!(wheel_event(3, false), (Wheel::Vertical, -3));
+        assert_eq!(wheel_event(-2, false), (Wheel::Vertical, 2));
+        assert_eq!(wheel_event(4, true), (Wheel::Horizontal, 4));
+        assert_eq!(wheel_event(-1, true), (Wheel::Horizontal, -1));
+    }
+
+    #[test]
+    fn side_buttons_are_sent() {
+        assert_eq!(Mouse::from(MouseButton::X1Button), Mouse::Side);
+        assert_eq!(Mouse::from(MouseButton::X2Button), Mouse::Extra);
     }
 }
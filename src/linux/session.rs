@@ -0,0 +1,55 @@
+use once_cell::sync::Lazy;
+use std::{env, fs, sync::Mutex};
+
+/// The kind of session inputbot runs in, see `session_type`.
+#[derive(Debug, Eq, PartialEq, Hash, Copy, Clone)]
+pub enum SessionType {
+    X11,
+    /// X is only used through XWayland, if at all, so it isn't relied on.
+    Wayland,
+    /// No graphical session was found, e.g. on a virtual console or in a service.
+    Tty,
+}
+
+static SESSION_TYPE: Lazy<SessionType> =
+    Lazy::new(|| match env::var("XDG_SESSION_TYPE").as_deref() {
+        Ok("wayland") => SessionType::Wayland,
+        Ok("x11") => SessionType::X11,
+        _ if env::var_os("WAYLAND_DISPLAY").is_some() => SessionType::Wayland,
+        _ if env::var_os("DISPLAY").is_some() => SessionType::X11,
+        _ => SessionType::Tty,
+    });
+static SCREEN_SIZE: Lazy<Mutex<Option<(i32, i32)>>> = Lazy::new(|| Mutex::new(None));
+
+/// Returns the kind of session inputbot runs in, from the environment of the process.
+///
+/// Outside of X11 sessions, lock key state is read from the keyboards and the cursor is
+/// positioned through an absolute pointer device instead of X.
+pub fn session_type() -> SessionType {
+    *SESSION_TYPE
+}
+
+/// Sets the size of the screen in pixels, used to position the cursor outside of X11
+/// sessions. By default it's the preferred mode of the first connected display. Has to be
+/// called before the first `MouseCursor::move_abs`.
+pub fn set_screen_size(width: i32, height: i32) {
+    *SCREEN_SIZE.lock().unwrap() = Some((width, height));
+}
+
+pub(crate) fn screen_size() -> Option<(i32, i32)> {
+    if let Some(size) = *SCREEN_SIZE.lock().unwrap() {
+        return Some(size);
+    }
+    fs::read_dir("/sys/class/drm")
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            fs::read_to_string(entry.path().join("status"))
+                .is_ok_and(|status| status.trim() == "connected")
+        })
+        .find_map(|entry| {
+            let modes = fs::read_to_string(entry.path().join("modes")).ok()?;
+            let (width, height) = modes.lines().next()?.split_once('x')?;
+            Some((width.parse().ok()?, height.parse().ok()?))
+        })
+}
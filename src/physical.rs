@@ -0,0 +1,340 @@
+use crate::public::{KeybdKey, KeybdKey::*};
+
+/// A key by its position on the keyboard rather than by what it types, identified by its
+/// Linux evdev key code (`KEY_*` in `linux/input-event-codes.h`). The same codes are used
+/// on every platform, and on Windows are converted from and to PC scancodes.
+#[derive(Debug, Eq, PartialEq, Hash, Ord, PartialOrd, Copy, Clone)]
+pub struct PhysicalKey(pub u16);
+
+// https://github.com/torvalds/linux/blob/master/include/uapi/linux/input-event-codes.h
+const KEY_CODES: &[(KeybdKey, u16)] = &[
+    (EscapeKey, 1),
+    (Numrow1Key, 2),
+    (Numrow2Key, 3),
+    (Numrow3Key, 4),
+    (Numrow4Key, 5),
+    (Numrow5Key, 6),
+    (Numrow6Key, 7),
+    (Numrow7Key, 8),
+    (Numrow8Key, 9),
+    (Numrow9Key, 10),
+    (Numrow0Key, 11),
+    (MinusKey, 12),
+    (EqualKey, 13),
+    (BackspaceKey, 14),
+    (TabKey, 15),
+    (QKey, 16),
+    (WKey, 17),
+    (EKey, 18),
+    (RKey, 19),
+    (TKey, 20),
+    (YKey, 21),
+    (UKey, 22),
+    (IKey, 23),
+    (OKey, 24),
+    (PKey, 25),
+    (LBracketKey, 26),
+    (RBracketKey, 27),
+    (EnterKey, 28),
+    (LControlKey, 29),
+    (AKey, 30),
+    (SKey, 31),
+    (DKey, 32),
+    (FKey, 33),
+    (GKey, 34),
+    (HKey, 35),
+    (JKey, 36),
+    (KKey, 37),
+    (LKey, 38),
+    (SemicolonKey, 39),
+    (QuoteKey, 40),
+    (BackquoteKey, 41),
+    (LShiftKey, 42),
+    (BackslashKey, 43),
+    (ZKey, 44),
+    (XKey, 45),
+    (CKey, 46),
+    (VKey, 47),
+    (BKey, 48),
+    (NKey, 49),
+    (MKey, 50),
+    (CommaKey, 51),
+    (PeriodKey, 52),
+    (SlashKey, 53),
+    (RShiftKey, 54),
+    (NumpadMultiplyKey, 55),
+    (LAltKey, 56),
+    (SpaceKey, 57),
+    (CapsLockKey, 58),
+    (F1Key, 59),
+    (F2Key, 60),
+    (F3Key, 61),
+    (F4Key, 62),
+    (F5Key, 63),
+    (F6Key, 64),
+    (F7Key, 65),
+    (F8Key, 66),
+    (F9Key, 67),
+    (F10Key, 68),
+    (NumLockKey, 69),
+    (ScrollLockKey, 70),
+    (Numpad7Key, 71),
+    (Numpad8Key, 72),
+    (Numpad9Key, 73),
+    (NumpadMinusKey, 74),
+    (Numpad4Key, 75),
+    (Numpad5Key, 76),
+    (Numpad6Key, 77),
+    (NumpadPlusKey, 78),
+    (Numpad1Key, 79),
+    (Numpad2Key, 80),
+    (Numpad3Key, 81),
+    (Numpad0Key, 82),
+    (NumpadDecimalKey, 83),
+    (ZenkakuHankakuKey, 85),
+    (IntlBackslashKey, 86),
+    (F11Key, 87),
+    (F12Key, 88),
+    (ConvertKey, 92),
+    (KanaKey, 93),
+    (NonConvertKey, 94),
+    (NumpadEnterKey, 96),
+    (RControlKey, 97),
+    (NumpadDivideKey, 98),
+    (PrintScreenKey, 99),
+    (RAltKey, 100),
+    (HomeKey, 102),
+    (UpKey, 103),
+    (PageUpKey, 104),
+    (LeftKey, 105),
+    (RightKey, 106),
+    (EndKey, 107),
+    (DownKey, 108),
+    (PageDownKey, 109),
+    (InsertKey, 110),
+    (DeleteKey, 111),
+    (VolumeMuteKey, 113),
+    (VolumeDownKey, 114),
+    (VolumeUpKey, 115),
+    (PauseKey, 119),
+    (HangulKey, 122),
+    (HanjaKey, 123),
+    (LSuper, 125),
+    (RSuper, 126),
+    (MenuKey, 127),
+    (BrowserStopKey, 128),
+    (LaunchApp2Key, 140),
+    (LaunchMailKey, 155),
+    (BrowserFavoritesKey, 156),
+    (LaunchApp1Key, 157),
+    (BrowserBackKey, 158),
+    (BrowserForwardKey, 159),
+    (MediaNextTrackKey, 163),
+    (MediaPlayPauseKey, 164),
+    (MediaPrevTrackKey, 165),
+    (MediaStopKey, 166),
+    (BrowserHomeKey, 172),
+    (BrowserRefreshKey, 173),
+    (F13Key, 183),
+    (F14Key, 184),
+    (F15Key, 185),
+    (F16Key, 186),
+    (F17Key, 187),
+    (F18Key, 188),
+    (F19Key, 189),
+    (F20Key, 190),
+    (F21Key, 191),
+    (F22Key, 192),
+    (F23Key, 193),
+    (F24Key, 194),
+    (BrowserSearchKey, 217),
+    (LaunchMediaKey, 226),
+];
+
+// Evdev codes of keys whose PC scancode has an 0xE0 prefix, with the scancode without it.
+// Keys which aren't listed here or below use their evdev code as scancode, up to F12.
+// Pause sends an 0xE1 sequence instead and has no scancode.
+// https://www.win.tue.nl/~aeb/linux/kbd/scancodes-1.html
+const EXTENDED_SCANCODES: &[(u16, u16)] = &[
+    (96, 0x1C),  // KP Enter
+    (97, 0x1D),  // Right Control
+    (98, 0x35),  // KP Slash
+    (99, 0x37),  // Print Screen
+    (100, 0x38), // Right Alt
+    (102, 0x47), // Home
+    (103, 0x48), // Up
+    (104, 0x49), // Page Up
+    (105, 0x4B), // Left
+    (106, 0x4D), // Right
+    (107, 0x4F), // End
+    (108, 0x50), // Down
+    (109, 0x51), // Page Down
+    (110, 0x52), // Insert
+    (111, 0x53), // Delete
+    (113, 0x20), // Mute
+    (114, 0x2E), // Volume Down
+    (115, 0x30), // Volume Up
+    (125, 0x5B), // Left Super
+    (126, 0x5C), // Right Super
+    (127, 0x5D), // Menu
+    (128, 0x68), // Browser Stop
+    (140, 0x21), // Calculator
+    (155, 0x6C), // Mail
+    (156, 0x66), // Browser Favorites
+    (157, 0x6B), // My Computer
+    (158, 0x6A), // Browser Back
+    (159, 0x69), // Browser Forward
+    (163, 0x19), // Next Track
+    (164, 0x22), // Play/Pause
+    (165, 0x10), // Previous Track
+    (166, 0x24), // Stop
+    (172, 0x32), // Browser Home
+    (173, 0x67), // Browser Refresh
+    (217, 0x65), // Browser Search
+    (226, 0x6D), // Media Select
+];
+
+// Keys without an 0xE0 prefix whose scancode differs from their evdev code.
+const OTHER_SCANCODES: &[(u16, u16)] = &[
+    (92, 0x79),  // Henkan
+    (93, 0x70),  // Katakana/Hiragana
+    (94, 0x7B),  // Muhenkan
+    (122, 0xF2), // Hangul
+    (123, 0xF1), // Hanja
+    (183, 0x64), // F13
+    (184, 0x65),
+    (185, 0x66),
+    (186, 0x67),
+    (187, 0x68),
+    (188, 0x69),
+    (189, 0x6A),
+    (190, 0x6B),
+    (191, 0x6C),
+    (192, 0x6D),
+    (193, 0x6E),
+    (194, 0x76), // F24
+];
+
+impl PhysicalKey {
+    /// Converts a PC scancode (set 1), as reported by Windows, where `extended` means it
+    /// has an 0xE0 prefix. Returns `None` for scancodes of no known key.
+    pub fn from_scancode(scancode: u16, extended: bool) -> Option<PhysicalKey> {
+        let code = if extended {
+            EXTENDED_SCANCODES
+                .iter()
+                .find(|&&(_, sc)| sc == scancode)
+                .map(|&(code, _)| code)?
+        } else if let Some(&(code, _)) = OTHER_SCANCODES.iter().find(|&&(_, sc)| sc == scancode) {
+            code
+        } else if (1..=88).contains(&scancode) {
+            scancode
+        } else {
+            return None;
+        };
+        Some(PhysicalKey(code))
+    }
+
+    /// Converts the key to a PC scancode (set 1) and whether it has an 0xE0 prefix. See
+    /// `from_scancode`.
+    pub fn scancode(self) -> Option<(u16, bool)> {
+        if let Some(&(_, sc)) = EXTENDED_SCANCODES.iter().find(|&&(code, _)| code == self.0) {
+            Some((sc, true))
+        } else if let Some(&(_, sc)) = OTHER_SCANCODES.iter().find(|&&(code, _)| code == self.0) {
+            Some((sc, false))
+        } else if (1..=88).contains(&self.0) {
+            Some((self.0, false))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the `KeybdKey` at this position. Keys without a variant of their own are
+    /// returned as `OtherKey`, see `KeybdKey::OtherKey`.
+    pub fn keybd_key(self) -> KeybdKey {
+        if let Some(&(key, _)) = KEY_CODES.iter().find(|&&(_, code)| code == self.0) {
+            return key;
+        }
+        // On Windows, `OtherKey` holds a virtual-key code.
+        #[cfg(target_os = "windows")]
+        {
+            use ::windows::Win32::UI::Input::KeyboardAndMouse::{
+                MapVirtualKeyW, MAP_VIRTUAL_KEY_TYPE,
+            };
+            let vk = self.scancode().map_or(0, |(scancode, extended)| {
+                let scancode = if extended {
+                    0xE000 | scancode
+                } else {
+                    scancode
+                };
+                // MAPVK_VSC_TO_VK_EX
+                unsafe { MapVirtualKeyW(scancode as u32, MAP_VIRTUAL_KEY_TYPE(3)) }
+            });
+            OtherKey(vk as u64)
+        }
+        #[cfg(not(target_os = "windows"))]
+        OtherKey(self.0 as u64)
+    }
+}
+
+impl KeybdKey {
+    /// Returns the position of the key on the keyboard, or `None` if it's not known.
+    pub fn physical_key(self) -> Option<PhysicalKey> {
+        if let Some(&(_, code)) = KEY_CODES.iter().find(|&&(key, _)| key == self) {
+            return Some(PhysicalKey(code));
+        }
+        match self {
+            #[cfg(target_os = "windows")]
+            OtherKey(vk) => {
+                use ::windows::Win32::UI::Input::KeyboardAndMouse::{
+                    MapVirtualKeyW, MAP_VIRTUAL_KEY_TYPE,
+                };
+                // MAPVK_VK_TO_VSC_EX
+                let scancode = unsafe { MapVirtualKeyW(vk as u32, MAP_VIRTUAL_KEY_TYPE(4)) };
+                PhysicalKey::from_scancode(scancode as u16 & 0xFF, scancode & 0xFF00 == 0xE000)
+            }
+            #[cfg(not(target_os = "windows"))]
+            OtherKey(code) => u16::try_from(code).ok().map(PhysicalKey),
+            _ => None,
+        }
+    }
+}
+
+impl From<PhysicalKey> for KeybdKey {
+    fn from(key: PhysicalKey) -> KeybdKey {
+        key.keybd_key()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use strum::IntoEnumIterator;
+
+    #[test]
+    fn physical_keys_convert() {
+        for key in KeybdKey::iter() {
+            let physical = key.physical_key().unwrap();
+            assert_eq!(physical.keybd_key(), key);
+            // Pause sends an 0xE1 sequence, which has no scancode.
+            if key == PauseKey {
+                assert_eq!(physical.scancode(), None);
+                continue;
+            }
+            let (scancode, extended) = physical.scancode().unwrap();
+            assert_eq!(
+                PhysicalKey::from_scancode(scancode, extended),
+                Some(physical)
+            );
+        }
+        assert_eq!(HomeKey.physical_key(), Some(PhysicalKey(102)));
+        assert_eq!(PhysicalKey(102).scancode(), Some((0x47, true)));
+        assert_eq!(PhysicalKey(71).keybd_key(), Numpad7Key);
+
+        #[cfg(target_os = "linux")]
+        {
+            // KEY_KPEQUAL
+            assert_eq!(PhysicalKey(117).keybd_key(), OtherKey(117));
+            assert_eq!(OtherKey(117).physical_key(), Some(PhysicalKey(117)));
+        }
+    }
+}
@@ -1,41 +1,239 @@
 use crate::common::*;
-use std::{thread::sleep, time::Duration};
+use std::{
+    thread::sleep,
+    time::{Duration, SystemTime},
+};
 
+use bitflags::bitflags;
 use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 
-#[cfg(feature = "serde")]
-use regex::Regex;
 #[cfg(feature = "serde")]
 use serde::{
     de::{Deserializer, Error},
-    Deserialize, Serialize,
+    Deserialize, Serialize, Serializer,
 };
-#[cfg(feature = "serde")]
 use thiserror::Error;
 
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
 pub enum BlockInput {
     Block,
     DontBlock,
 }
 
-#[cfg(feature = "serde")]
-fn other_key_regex() -> &'static Regex {
-    use std::sync::OnceLock;
+/// The kind of keyboard event reported by the platform.
+#[derive(Debug, Eq, PartialEq, Hash, Copy, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum KeyEvent {
+    /// The key went down.
+    Press,
+    /// The key is held down and generated an autorepeat.
+    Repeat,
+    /// The key went up.
+    Release,
+}
 
-    static OTHER_KEY: OnceLock<Regex> = OnceLock::new();
-    OTHER_KEY.get_or_init(|| Regex::new(r#"OtherKey\((\d+)\)"#).unwrap())
+/// Options which control when the bind of a key or button fires.
+#[derive(Debug, Default, Eq, PartialEq, Hash, Copy, Clone)]
+pub struct BindOptions {
+    /// Fire the bind again for every autorepeat while the key is held. By default binds
+    /// only fire on the initial press.
+    pub repeat: bool,
+    /// Ignore events sent by inputbot itself, e.g. from `KeybdKey::press` in a callback or
+    /// from a remap. The binds and gestures of the key won't fire for them.
+    pub ignore_injected: bool,
 }
 
-#[cfg(feature = "serde")]
-fn other_mouse_regex() -> &'static Regex {
-    use std::sync::OnceLock;
+/// The lock states of the keyboard, see `lock_state`.
+#[derive(Debug, Default, Eq, PartialEq, Hash, Copy, Clone)]
+pub struct LockState {
+    pub caps_lock: bool,
+    pub num_lock: bool,
+    pub scroll_lock: bool,
+    pub compose: bool,
+    pub kana: bool,
+}
 
-    static OTHER_KEY: OnceLock<Regex> = OnceLock::new();
-    OTHER_KEY.get_or_init(|| Regex::new(r#"MouseButton\((\d+)\)"#).unwrap())
+bitflags! {
+    /// The modifier keys which are held and the lock states which are on.
+    #[derive(Debug, Default, Eq, PartialEq, Hash, Copy, Clone)]
+    pub struct Modifiers: u16 {
+        const LSHIFT = 1 << 0;
+        const RSHIFT = 1 << 1;
+        const LCONTROL = 1 << 2;
+        const RCONTROL = 1 << 3;
+        const LALT = 1 << 4;
+        const RALT = 1 << 5;
+        const LSUPER = 1 << 6;
+        const RSUPER = 1 << 7;
+        /// Set together with `RALT`, which is AltGr on most non-US layouts. See
+        /// `set_right_alt_is_altgr` for layouts where it isn't.
+        const ALTGR = 1 << 8;
+        const CAPS_LOCK = 1 << 9;
+        const NUM_LOCK = 1 << 10;
+        const SCROLL_LOCK = 1 << 11;
+
+        const SHIFT = Self::LSHIFT.bits() | Self::RSHIFT.bits();
+        const CONTROL = Self::LCONTROL.bits() | Self::RCONTROL.bits();
+        const ALT = Self::LALT.bits() | Self::RALT.bits();
+        const SUPER = Self::LSUPER.bits() | Self::RSUPER.bits();
+    }
+}
+
+const MODIFIER_KEYS: [KeybdKey; 8] = [
+    KeybdKey::LShiftKey,
+    KeybdKey::RShiftKey,
+    KeybdKey::LControlKey,
+    KeybdKey::RControlKey,
+    KeybdKey::LAltKey,
+    KeybdKey::RAltKey,
+    KeybdKey::LSuper,
+    KeybdKey::RSuper,
+];
+
+static RIGHT_ALT_IS_ALTGR: AtomicBool = AtomicBool::new(true);
+
+/// Sets whether right Alt is AltGr, which sets `Modifiers::ALTGR` along with
+/// `Modifiers::RALT`. Defaults to true, as on most non-US layouts.
+pub fn set_right_alt_is_altgr(altgr: bool) {
+    RIGHT_ALT_IS_ALTGR.store(altgr, Ordering::Relaxed);
+}
+
+impl Modifiers {
+    /// Returns the modifiers which are held and the lock states which are on right now.
+    pub fn current() -> Modifiers {
+        Modifiers::of_state(
+            MODIFIER_KEYS.into_iter().filter(|key| key.is_pressed()),
+            crate::lock_state(),
+        )
+    }
+
+    // Returns the modifiers while the given keys are held, in the given lock state.
+    fn of_state<I: IntoIterator<Item = KeybdKey>>(held: I, locks: LockState) -> Modifiers {
+        let mut modifiers = held
+            .into_iter()
+            .map(Modifiers::of_key)
+            .collect::<Modifiers>();
+        modifiers.set(Modifiers::CAPS_LOCK, locks.caps_lock);
+        modifiers.set(Modifiers::NUM_LOCK, locks.num_lock);
+        modifiers.set(Modifiers::SCROLL_LOCK, locks.scroll_lock);
+        modifiers
+    }
+
+    /// Returns the flags a modifier key sets while held, or no flags for other keys.
+    pub fn of_key(key: KeybdKey) -> Modifiers {
+        match key {
+            KeybdKey::LShiftKey => Modifiers::LSHIFT,
+            KeybdKey::RShiftKey => Modifiers::RSHIFT,
+            KeybdKey::LControlKey => Modifiers::LCONTROL,
+            KeybdKey::RControlKey => Modifiers::RCONTROL,
+            KeybdKey::LAltKey => Modifiers::LALT,
+            KeybdKey::RAltKey if RIGHT_ALT_IS_ALTGR.load(Ordering::Relaxed) => {
+                Modifiers::RALT | Modifiers::ALTGR
+            }
+            KeybdKey::RAltKey => Modifiers::RALT,
+            KeybdKey::LSuper => Modifiers::LSUPER,
+            KeybdKey::RSuper => Modifiers::RSUPER,
+            _ => Modifiers::empty(),
+        }
+    }
+}
+
+/// A key or mouse button.
+#[derive(Debug, Eq, PartialEq, Hash, Copy, Clone)]
+pub enum Input {
+    Keybd(KeybdKey),
+    Mouse(MouseButton),
+}
+
+/// A key or mouse button event, as delivered to the listener set with
+/// `set_event_listener` and to event binds like `KeybdKey::bind_event`.
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub struct InputEvent {
+    pub input: Input,
+    pub event: KeyEvent,
+    /// The event was sent by inputbot itself rather than by a device.
+    pub is_injected: bool,
+    /// The modifiers after the event, so a press of a modifier key includes itself.
+    pub modifiers: Modifiers,
+    /// When inputbot received the event.
+    pub timestamp: SystemTime,
+    /// The name of the device the event came from. Only known on Linux.
+    pub device: Option<String>,
+}
+
+/// Gestures which can be bound with `KeybdKey::bind_gesture` and
+/// `MouseButton::bind_gesture`.
+#[derive(Debug, Eq, PartialEq, Hash, Copy, Clone)]
+pub enum Gesture {
+    /// A press shorter than `GestureTiming::hold`.
+    Tap,
+    /// Two taps in quick succession.
+    DoubleTap,
+    /// A press held for longer than `GestureTiming::hold`. Fires while still held.
+    Hold,
+}
+
+/// Timing used to tell gestures apart, see `set_gesture_timing`.
+#[derive(Debug, Eq, PartialEq, Hash, Copy, Clone)]
+pub struct GestureTiming {
+    /// Presses shorter than this are taps, longer ones are holds.
+    pub hold: Duration,
+    /// Longest time after a tap in which a second tap makes it a double tap.
+    pub double_tap: Duration,
+}
+
+/// How a dual-role key decides whether it is being held, see `KeybdKey::dual_role`.
+#[derive(Debug, Default, Eq, PartialEq, Hash, Copy, Clone)]
+pub enum DualRoleMode {
+    /// The key is held once it has been down for longer than the tapping term.
+    #[default]
+    Timeout,
+    /// The key is also held when another key is pressed and released while it is down.
+    PermissiveHold,
+    /// The key is also held as soon as another key is pressed while it is down.
+    HoldOnOtherKeyPress,
+}
+
+/// Options for dual-role keys, see `set_dual_role_options`.
+#[derive(Debug, Eq, PartialEq, Hash, Copy, Clone)]
+pub struct DualRoleOptions {
+    /// Presses longer than this always hold the key.
+    pub tapping_term: Duration,
+    pub mode: DualRoleMode,
+}
+
+impl Default for DualRoleOptions {
+    fn default() -> Self {
+        DualRoleOptions {
+            tapping_term: Duration::from_millis(200),
+            mode: DualRoleMode::default(),
+        }
+    }
+}
+
+impl Default for GestureTiming {
+    fn default() -> Self {
+        GestureTiming {
+            hold: Duration::from_millis(500),
+            double_tap: Duration::from_millis(250),
+        }
+    }
+}
+
+// Returns the code of a lowercase name like `otherkey(42)`.
+fn parse_code<T: std::str::FromStr<Err = std::num::ParseIntError>>(
+    s_lower: &str,
+    prefix: &str,
+) -> Option<Result<T, ParseError>> {
+    let code = s_lower.strip_prefix(prefix)?.strip_suffix(')')?;
+    Some(code.parse().map_err(ParseError::from))
 }
 
-#[cfg(feature = "serde")]
 fn keyboard_canonical_names() -> &'static HashMap<String, KeybdKey> {
     use std::sync::OnceLock;
 
@@ -51,7 +249,6 @@ fn keyboard_canonical_names() -> &'static HashMap<String, KeybdKey> {
     })
 }
 
-#[cfg(feature = "serde")]
 fn keyboard_canonical_names_lower() -> &'static HashMap<String, KeybdKey> {
     use std::sync::OnceLock;
     static KEYBOARD_CANONICAL_NAMES_LOWER: OnceLock<HashMap<String, KeybdKey>> = OnceLock::new();
@@ -59,11 +256,10 @@ fn keyboard_canonical_names_lower() -> &'static HashMap<String, KeybdKey> {
     KEYBOARD_CANONICAL_NAMES_LOWER.get_or_init(|| {
         keyboard_canonical_names()
             .iter()
-            .map(|(k, v)| (k.to_lowercase(), v.clone()))
+            .map(|(k, v)| (k.to_lowercase(), *v))
             .collect()
     })
 }
-#[cfg(feature = "serde")]
 fn mouse_canonical_names() -> &'static HashMap<String, MouseButton> {
     use std::sync::OnceLock;
 
@@ -78,7 +274,6 @@ fn mouse_canonical_names() -> &'static HashMap<String, MouseButton> {
     })
 }
 
-#[cfg(feature = "serde")]
 fn mouse_canonical_names_lower() -> &'static HashMap<String, MouseButton> {
     use std::sync::OnceLock;
 
@@ -86,14 +281,12 @@ fn mouse_canonical_names_lower() -> &'static HashMap<String, MouseButton> {
     MOUSE_CANONICAL_NAMES_LOWER.get_or_init(|| {
         mouse_canonical_names()
             .iter()
-            .map(|(k, v)| (k.to_lowercase(), v.clone()))
+            .map(|(k, v)| (k.to_lowercase(), *v))
             .collect()
     })
 }
 
 #[derive(Debug, Eq, PartialEq, Hash, Copy, Clone, EnumIter)]
-#[cfg_attr(feature = "serde", derive(Serialize))]
-
 pub enum KeybdKey {
     BackspaceKey,
     TabKey,
@@ -110,6 +303,9 @@ pub enum KeybdKey {
     DownKey,
     InsertKey,
     DeleteKey,
+    PrintScreenKey,
+    PauseKey,
+    MenuKey,
     Numrow0Key,
     Numrow1Key,
     Numrow2Key,
@@ -158,6 +354,12 @@ pub enum KeybdKey {
     Numpad7Key,
     Numpad8Key,
     Numpad9Key,
+    NumpadPlusKey,
+    NumpadMinusKey,
+    NumpadMultiplyKey,
+    NumpadDivideKey,
+    NumpadDecimalKey,
+    NumpadEnterKey,
     F1Key,
     F2Key,
     F3Key,
@@ -195,6 +397,10 @@ pub enum KeybdKey {
     BrowserBackKey,
     BrowserForwardKey,
     BrowserRefreshKey,
+    BrowserStopKey,
+    BrowserSearchKey,
+    BrowserFavoritesKey,
+    BrowserHomeKey,
 
     VolumeMuteKey,
     VolumeDownKey,
@@ -205,6 +411,13 @@ pub enum KeybdKey {
     MediaStopKey,
     MediaPlayPauseKey,
 
+    LaunchMailKey,
+    LaunchMediaKey,
+    /// Usually opens the file manager ("My Computer").
+    LaunchApp1Key,
+    /// Usually opens the calculator.
+    LaunchApp2Key,
+
     BackquoteKey,
     SlashKey,
     BackslashKey,
@@ -216,13 +429,27 @@ pub enum KeybdKey {
     LBracketKey,
     RBracketKey,
     EqualKey,
-
+    /// The extra key next to left Shift on ISO keyboards, which types `<` and `>` on many
+    /// layouts.
+    IntlBackslashKey,
+
+    /// Katakana/Hiragana on Japanese keyboards.
+    KanaKey,
+    ZenkakuHankakuKey,
+    /// Henkan on Japanese keyboards.
+    ConvertKey,
+    /// Muhenkan on Japanese keyboards.
+    NonConvertKey,
+    HangulKey,
+    HanjaKey,
+
+    /// A key without a variant of its own. Holds a virtual-key code on Windows, and an
+    /// evdev key code (see `PhysicalKey`) on Linux.
     #[strum(disabled)]
     OtherKey(u64),
 }
 
 #[derive(Debug, Eq, PartialEq, Hash, Copy, Clone, EnumIter)]
-#[cfg_attr(feature = "serde", derive(Serialize))]
 pub enum MouseButton {
     LeftButton,
     MiddleButton,
@@ -237,10 +464,23 @@ pub enum MouseButton {
 
 pub struct MouseCursor;
 
+#[derive(Debug, Error)]
+pub enum CursorError {
+    #[error("can't find the screen size to position the cursor, use set_screen_size")]
+    UnknownScreenSize,
+    #[error("can't position the cursor: {0}")]
+    Other(String),
+}
+
 pub struct MouseWheel;
 
 impl KeybdKey {
     pub fn bind<F: Fn() + Send + Sync + 'static>(self, callback: F) {
+        self.bind_event(move |_| callback());
+    }
+
+    /// Like `bind`, but the callback receives the event which fired it.
+    pub fn bind_event<F: Fn(&InputEvent) + Send + Sync + 'static>(self, callback: F) {
         KEYBD_BINDS
             .lock()
             .unwrap()
@@ -249,6 +489,11 @@ impl KeybdKey {
 
     #[cfg(target_os = "windows")]
     pub fn bind_release<F: Fn() + Send + Sync + 'static>(self, callback: F) {
+        self.bind_release_event(move |_| callback());
+    }
+
+    #[cfg(target_os = "windows")]
+    pub fn bind_release_event<F: Fn(&InputEvent) + Send + Sync + 'static>(self, callback: F) {
         KEYBD_RELEASE_BINDS
             .lock()
             .unwrap()
@@ -256,6 +501,10 @@ impl KeybdKey {
     }
 
     pub fn block_bind<F: Fn() + Send + Sync + 'static>(self, callback: F) {
+        self.block_bind_event(move |_| callback());
+    }
+
+    pub fn block_bind_event<F: Fn(&InputEvent) + Send + Sync + 'static>(self, callback: F) {
         KEYBD_BINDS
             .lock()
             .unwrap()
@@ -263,6 +512,13 @@ impl KeybdKey {
     }
 
     pub fn blockable_bind<F: Fn() -> BlockInput + Send + Sync + 'static>(self, callback: F) {
+        self.blockable_bind_event(move |_| callback());
+    }
+
+    pub fn blockable_bind_event<F: Fn(&InputEvent) -> BlockInput + Send + Sync + 'static>(
+        self,
+        callback: F,
+    ) {
         KEYBD_BINDS
             .lock()
             .unwrap()
@@ -272,7 +528,7 @@ impl KeybdKey {
     pub fn bind_all<F: Fn(KeybdKey) + Send + Sync + Clone + 'static>(callback: F) {
         for key in KeybdKey::iter() {
             let callback = callback.clone();
-            let fire = move || {
+            let fire = move |_: &InputEvent| {
                 callback(key);
             };
 
@@ -287,7 +543,7 @@ impl KeybdKey {
     pub fn bind_all_release<F: Fn(KeybdKey) + Send + Sync + Clone + 'static>(callback: F) {
         for key in KeybdKey::iter() {
             let callback = callback.clone();
-            let fire = move || {
+            let fire = move |_: &InputEvent| {
                 callback(key);
             };
 
@@ -306,6 +562,77 @@ impl KeybdKey {
         KEYBD_BINDS.lock().unwrap().remove(&self);
     }
 
+    /// Binds a callback to a gesture made with this key.
+    ///
+    /// The key is blocked while it has gesture binds, so that it doesn't also type its
+    /// character. If the key also has a `DoubleTap` bind, `Tap` only fires once the
+    /// double tap time has passed without a second tap.
+    pub fn bind_gesture<F: Fn() + Send + Sync + 'static>(self, gesture: Gesture, callback: F) {
+        KEYBD_GESTURE_BINDS
+            .lock()
+            .unwrap()
+            .insert((self, gesture), Arc::new(callback));
+    }
+
+    pub fn unbind_gesture(self, gesture: Gesture) {
+        KEYBD_GESTURE_BINDS.lock().unwrap().remove(&(self, gesture));
+    }
+
+    /// Makes this key act as `to` in all applications.
+    ///
+    /// The key's own events are blocked, and `to` is pressed and released in their place.
+    /// Events sent by inputbot are never remapped, so keys can be swapped. On Linux, this
+    /// needs `set_grab_keyboards`.
+    pub fn remap(self, to: KeybdKey) {
+        REMAPS.lock().unwrap().insert(self, to);
+    }
+
+    pub fn remove_remap(self) {
+        REMAPS.lock().unwrap().remove(&self);
+    }
+
+    /// Makes this key act as `tap` when tapped and as `hold` while held, e.g. CapsLock as
+    /// Escape and LeftControl.
+    ///
+    /// The key's own events are blocked and the events of `tap` or `hold` are sent in its
+    /// place. Events of other keys pressed before the role is decided are held back and
+    /// replayed after it. On Linux, this needs `set_grab_keyboards`.
+    pub fn dual_role(self, tap: KeybdKey, hold: KeybdKey) {
+        DUAL_ROLES
+            .lock()
+            .unwrap()
+            .insert(self, DualRole { tap, hold });
+    }
+
+    pub fn remove_dual_role(self) {
+        DUAL_ROLES.lock().unwrap().remove(&self);
+    }
+
+    /// Sets the options used by the binds of this key. Options are kept when the key is
+    /// rebound or unbound.
+    pub fn set_bind_options(self, options: BindOptions) {
+        KEYBD_BIND_OPTIONS.lock().unwrap().insert(self, options);
+    }
+
+    /// Returns the options used by the binds of this key.
+    pub fn bind_options(self) -> BindOptions {
+        KEYBD_BIND_OPTIONS
+            .lock()
+            .unwrap()
+            .get(&self)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Turns a key which supports toggling (ScrollLock, NumLock, CapsLock) on or off, by
+    /// tapping it if it isn't in that state already.
+    pub fn set_toggled(self, on: bool) {
+        if self.is_toggled() != on {
+            self.press();
+            self.release();
+        }
+    }
+
     // the canonical_name is guaranteed to roundtrip to and from the serialization format.
     pub fn canonical_name(self) -> String {
         match self {
@@ -314,6 +641,84 @@ impl KeybdKey {
             _ => format!("{}", self),
         }
     }
+
+    /// Returns the name of the key for showing to users, like "Left Shift" or "Num 5".
+    /// Unlike `canonical_name`, it can't be parsed back with `from_str`, and it can be
+    /// replaced with `set_display_names`.
+    pub fn display_name(self) -> String {
+        if let Some(name) = DISPLAY_NAMES.lock().unwrap().get(&Input::Keybd(self)) {
+            return name.clone();
+        }
+        match self {
+            KeybdKey::LSuper | KeybdKey::RSuper => split_words(&self.to_string()),
+            KeybdKey::LControlKey => "Left Ctrl".to_owned(),
+            KeybdKey::RControlKey => "Right Ctrl".to_owned(),
+            KeybdKey::BrowserBackKey => "Browser Back".to_owned(),
+            KeybdKey::BrowserForwardKey => "Browser Forward".to_owned(),
+            KeybdKey::BrowserRefreshKey => "Browser Refresh".to_owned(),
+            KeybdKey::VolumeMuteKey => "Mute".to_owned(),
+            KeybdKey::MediaNextTrackKey => "Next Track".to_owned(),
+            KeybdKey::MediaPrevTrackKey => "Previous Track".to_owned(),
+            KeybdKey::MediaStopKey => "Stop".to_owned(),
+            KeybdKey::MediaPlayPauseKey => "Play/Pause".to_owned(),
+            KeybdKey::NumpadEnterKey => "Num Enter".to_owned(),
+            KeybdKey::LaunchApp1Key => "Launch App 1".to_owned(),
+            KeybdKey::LaunchApp2Key => "Launch App 2".to_owned(),
+            KeybdKey::IntlBackslashKey => "ISO Backslash".to_owned(),
+            KeybdKey::OtherKey(code) => format!("Key {code}"),
+            _ => {
+                if let Some(c) = from_keybd_key(self) {
+                    if self.is_numpad() {
+                        format!("Num {c}")
+                    } else {
+                        c.to_uppercase().to_string()
+                    }
+                } else {
+                    split_words(&self.to_string())
+                }
+            }
+        }
+    }
+
+    /// Finds the key with this display name, ignoring case. See `display_name`.
+    pub fn from_display_name(name: &str) -> Option<KeybdKey> {
+        KeybdKey::iter().find(|key| key.display_name().eq_ignore_ascii_case(name.trim()))
+    }
+
+    fn is_numpad(self) -> bool {
+        matches!(
+            self,
+            KeybdKey::Numpad0Key
+                | KeybdKey::Numpad1Key
+                | KeybdKey::Numpad2Key
+                | KeybdKey::Numpad3Key
+                | KeybdKey::Numpad4Key
+                | KeybdKey::Numpad5Key
+                | KeybdKey::Numpad6Key
+                | KeybdKey::Numpad7Key
+                | KeybdKey::Numpad8Key
+                | KeybdKey::Numpad9Key
+                | KeybdKey::NumpadPlusKey
+                | KeybdKey::NumpadMinusKey
+                | KeybdKey::NumpadMultiplyKey
+                | KeybdKey::NumpadDivideKey
+                | KeybdKey::NumpadDecimalKey
+        )
+    }
+}
+
+// Splits a name like "PageUp" into "Page Up".
+fn split_words(name: &str) -> String {
+    let mut words = String::new();
+    let mut prev_lowercase = false;
+    for c in name.chars() {
+        if c.is_uppercase() && prev_lowercase {
+            words.push(' ');
+        }
+        prev_lowercase = c.is_lowercase();
+        words.push(c);
+    }
+    words
 }
 
 impl std::fmt::Display for KeybdKey {
@@ -337,6 +742,9 @@ impl std::fmt::Display for KeybdKey {
                 KeybdKey::DownKey => "Down",
                 KeybdKey::InsertKey => "Insert",
                 KeybdKey::DeleteKey => "Delete",
+                KeybdKey::PrintScreenKey => "PrintScreen",
+                KeybdKey::PauseKey => "Pause",
+                KeybdKey::MenuKey => "Menu",
                 KeybdKey::Numrow0Key => "0",
                 KeybdKey::Numrow1Key => "1",
                 KeybdKey::Numrow2Key => "2",
@@ -399,6 +807,12 @@ impl std::fmt::Display for KeybdKey {
                 KeybdKey::Numpad7Key => "NumPad7",
                 KeybdKey::Numpad8Key => "NumPad8",
                 KeybdKey::Numpad9Key => "NumPad9",
+                KeybdKey::NumpadPlusKey => "NumPadPlus",
+                KeybdKey::NumpadMinusKey => "NumPadMinus",
+                KeybdKey::NumpadMultiplyKey => "NumPadMultiply",
+                KeybdKey::NumpadDivideKey => "NumPadDivide",
+                KeybdKey::NumpadDecimalKey => "NumPadDecimal",
+                KeybdKey::NumpadEnterKey => "NumPadEnter",
                 KeybdKey::F1Key => "F1",
                 KeybdKey::F2Key => "F2",
                 KeybdKey::F3Key => "F3",
@@ -435,6 +849,10 @@ impl std::fmt::Display for KeybdKey {
                 KeybdKey::BrowserBackKey => "Back",
                 KeybdKey::BrowserForwardKey => "Forward",
                 KeybdKey::BrowserRefreshKey => "Refresh",
+                KeybdKey::BrowserStopKey => "BrowserStop",
+                KeybdKey::BrowserSearchKey => "BrowserSearch",
+                KeybdKey::BrowserFavoritesKey => "BrowserFavorites",
+                KeybdKey::BrowserHomeKey => "BrowserHome",
                 KeybdKey::VolumeMuteKey => "VolumeMute",
                 KeybdKey::VolumeDownKey => "VolumeDown",
                 KeybdKey::VolumeUpKey => "VolumeUp",
@@ -442,6 +860,10 @@ impl std::fmt::Display for KeybdKey {
                 KeybdKey::MediaPrevTrackKey => "MediaPrevious",
                 KeybdKey::MediaStopKey => "MediaStop",
                 KeybdKey::MediaPlayPauseKey => "MediaPlay",
+                KeybdKey::LaunchMailKey => "LaunchMail",
+                KeybdKey::LaunchMediaKey => "LaunchMedia",
+                KeybdKey::LaunchApp1Key => "LaunchApp1",
+                KeybdKey::LaunchApp2Key => "LaunchApp2",
                 KeybdKey::BackquoteKey => "Backquote",
                 KeybdKey::SlashKey => "Slash",
                 KeybdKey::BackslashKey => "Backslash",
@@ -453,13 +875,19 @@ impl std::fmt::Display for KeybdKey {
                 KeybdKey::LBracketKey => "LeftBracket",
                 KeybdKey::RBracketKey => "RightBracket",
                 KeybdKey::EqualKey => "Equal",
+                KeybdKey::IntlBackslashKey => "IntlBackslash",
+                KeybdKey::KanaKey => "Kana",
+                KeybdKey::ZenkakuHankakuKey => "ZenkakuHankaku",
+                KeybdKey::ConvertKey => "Convert",
+                KeybdKey::NonConvertKey => "NonConvert",
+                KeybdKey::HangulKey => "Hangul",
+                KeybdKey::HanjaKey => "Hanja",
                 KeybdKey::OtherKey(code) => return write!(f, "OtherKey({code})"),
             }
         )
     }
 }
 
-#[cfg(feature = "serde")]
 #[derive(Debug, Error)]
 pub enum ParseError {
     #[error("Unable to parse the keycode value")]
@@ -473,9 +901,16 @@ pub enum ParseError {
         val: String,
         backtrace: std::backtrace::Backtrace,
     },
+    #[error("Empty hotkey")]
+    EmptyHotkey,
+    #[error("Missing key after '+' in '{hotkey}'")]
+    MissingKey { hotkey: String },
+    #[error("Unknown key or button '{name}'")]
+    UnknownKey { name: String },
+    #[error("'{name}' isn't a modifier, only the last key of a hotkey can be")]
+    UnknownModifier { name: String },
 }
 
-#[cfg(feature = "serde")]
 impl std::str::FromStr for KeybdKey {
     type Err = ParseError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
@@ -484,17 +919,33 @@ impl std::str::FromStr for KeybdKey {
             return Ok(*k);
         }
         match s_lower.as_str() {
-            "leftwindows" => return Ok(KeybdKey::LSuper),
-            "leftcommand" => return Ok(KeybdKey::LSuper),
-            "rightwindows" => return Ok(KeybdKey::RSuper),
-            "rightcommand" => return Ok(KeybdKey::RSuper),
+            "leftwindows" | "leftcommand" | "leftmeta" | "lsuper" => return Ok(KeybdKey::LSuper),
+            "rightwindows" | "rightcommand" | "rightmeta" | "rsuper" => {
+                return Ok(KeybdKey::RSuper)
+            }
+            "lshift" => return Ok(KeybdKey::LShiftKey),
+            "rshift" => return Ok(KeybdKey::RShiftKey),
+            "lctrl" | "leftctrl" | "lcontrol" => return Ok(KeybdKey::LControlKey),
+            "rctrl" | "rightctrl" | "rcontrol" => return Ok(KeybdKey::RControlKey),
+            "lalt" => return Ok(KeybdKey::LAltKey),
+            "ralt" | "altgr" => return Ok(KeybdKey::RAltKey),
+            "return" => return Ok(KeybdKey::EnterKey),
+            "esc" => return Ok(KeybdKey::EscapeKey),
+            "del" => return Ok(KeybdKey::DeleteKey),
+            "ins" => return Ok(KeybdKey::InsertKey),
+            "pgup" => return Ok(KeybdKey::PageUpKey),
+            "pgdn" => return Ok(KeybdKey::PageDownKey),
+            "print" | "prtsc" | "sysrq" => return Ok(KeybdKey::PrintScreenKey),
+            "break" => return Ok(KeybdKey::PauseKey),
+            "apps" | "application" | "contextmenu" => return Ok(KeybdKey::MenuKey),
+            "numpadadd" => return Ok(KeybdKey::NumpadPlusKey),
+            "numpadsubtract" => return Ok(KeybdKey::NumpadMinusKey),
+            "henkan" => return Ok(KeybdKey::ConvertKey),
+            "muhenkan" => return Ok(KeybdKey::NonConvertKey),
             _ => {}
         }
-        if let Some(caps) = other_key_regex().captures(s) {
-            let v = &caps[1]
-                .parse::<u64>()
-                .map_err(|err| Into::<ParseError>::into(err))?;
-            return Ok(KeybdKey::OtherKey(*v));
+        if let Some(code) = parse_code(&s_lower, "otherkey(") {
+            return Ok(KeybdKey::OtherKey(code?));
         }
 
         Err(ParseError::UnknownFormat {
@@ -504,6 +955,17 @@ impl std::str::FromStr for KeybdKey {
     }
 }
 
+// Serialized as the canonical name, so that it reads back with `Deserialize`.
+#[cfg(feature = "serde")]
+impl Serialize for KeybdKey {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.canonical_name())
+    }
+}
+
 #[cfg(feature = "serde")]
 impl<'de> Deserialize<'de> for KeybdKey {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
@@ -517,6 +979,11 @@ impl<'de> Deserialize<'de> for KeybdKey {
 
 impl MouseButton {
     pub fn bind<F: Fn() + Send + Sync + 'static>(self, callback: F) {
+        self.bind_event(move |_| callback());
+    }
+
+    /// Like `bind`, but the callback receives the event which fired it.
+    pub fn bind_event<F: Fn(&InputEvent) + Send + Sync + 'static>(self, callback: F) {
         MOUSE_BINDS
             .lock()
             .unwrap()
@@ -525,6 +992,11 @@ impl MouseButton {
 
     #[cfg(target_os = "windows")]
     pub fn bind_release<F: Fn() + Send + Sync + 'static>(self, callback: F) {
+        self.bind_release_event(move |_| callback());
+    }
+
+    #[cfg(target_os = "windows")]
+    pub fn bind_release_event<F: Fn(&InputEvent) + Send + Sync + 'static>(self, callback: F) {
         MOUSE_RELEASE_BINDS
             .lock()
             .unwrap()
@@ -532,6 +1004,10 @@ impl MouseButton {
     }
 
     pub fn block_bind<F: Fn() + Send + Sync + 'static>(self, callback: F) {
+        self.block_bind_event(move |_| callback());
+    }
+
+    pub fn block_bind_event<F: Fn(&InputEvent) + Send + Sync + 'static>(self, callback: F) {
         MOUSE_BINDS
             .lock()
             .unwrap()
@@ -539,6 +1015,13 @@ impl MouseButton {
     }
 
     pub fn blockable_bind<F: Fn() -> BlockInput + Send + Sync + 'static>(self, callback: F) {
+        self.blockable_bind_event(move |_| callback());
+    }
+
+    pub fn blockable_bind_event<F: Fn(&InputEvent) -> BlockInput + Send + Sync + 'static>(
+        self,
+        callback: F,
+    ) {
         MOUSE_BINDS
             .lock()
             .unwrap()
@@ -548,7 +1031,7 @@ impl MouseButton {
     pub fn bind_all<F: Fn(MouseButton) + Send + Sync + Clone + 'static>(callback: F) {
         for btn in MouseButton::iter() {
             let callback = callback.clone();
-            let fire = move || {
+            let fire = move |_: &InputEvent| {
                 callback(btn);
             };
 
@@ -563,7 +1046,7 @@ impl MouseButton {
     pub fn bind_all_release<F: Fn(MouseButton) + Send + Sync + Clone + 'static>(callback: F) {
         for btn in MouseButton::iter() {
             let callback = callback.clone();
-            let fire = move || {
+            let fire = move |_: &InputEvent| {
                 callback(btn);
             };
 
@@ -582,9 +1065,61 @@ impl MouseButton {
         MOUSE_BINDS.lock().unwrap().remove(&self);
     }
 
+    /// Binds a callback to a gesture made with this button. See
+    /// `KeybdKey::bind_gesture`.
+    pub fn bind_gesture<F: Fn() + Send + Sync + 'static>(self, gesture: Gesture, callback: F) {
+        MOUSE_GESTURE_BINDS
+            .lock()
+            .unwrap()
+            .insert((self, gesture), Arc::new(callback));
+    }
+
+    pub fn unbind_gesture(self, gesture: Gesture) {
+        MOUSE_GESTURE_BINDS.lock().unwrap().remove(&(self, gesture));
+    }
+
+    /// Sets the options used by the binds of this button. Options are kept when the button
+    /// is rebound or unbound.
+    pub fn set_bind_options(self, options: BindOptions) {
+        MOUSE_BIND_OPTIONS.lock().unwrap().insert(self, options);
+    }
+
+    /// Returns the options used by the binds of this button.
+    pub fn bind_options(self) -> BindOptions {
+        MOUSE_BIND_OPTIONS
+            .lock()
+            .unwrap()
+            .get(&self)
+            .copied()
+            .unwrap_or_default()
+    }
+
     pub fn canonical_name(self) -> String {
         format!("{}", &self)
     }
+
+    /// Returns the name of the button for showing to users, like "Left Button". See
+    /// `KeybdKey::display_name`.
+    pub fn display_name(self) -> String {
+        if let Some(name) = DISPLAY_NAMES.lock().unwrap().get(&Input::Mouse(self)) {
+            return name.clone();
+        }
+        match self {
+            MouseButton::LeftButton => "Left Button".to_owned(),
+            MouseButton::MiddleButton => "Middle Button".to_owned(),
+            MouseButton::RightButton => "Right Button".to_owned(),
+            MouseButton::X1Button => "Back Button".to_owned(),
+            MouseButton::X2Button => "Forward Button".to_owned(),
+            MouseButton::MousewheelUp => "Wheel Up".to_owned(),
+            MouseButton::MousewheelDown => "Wheel Down".to_owned(),
+            MouseButton::OtherButton(code) => format!("Button {code}"),
+        }
+    }
+
+    /// Finds the button with this display name, ignoring case. See `display_name`.
+    pub fn from_display_name(name: &str) -> Option<MouseButton> {
+        MouseButton::iter().find(|button| button.display_name().eq_ignore_ascii_case(name.trim()))
+    }
 }
 
 impl std::fmt::Display for MouseButton {
@@ -606,7 +1141,6 @@ impl std::fmt::Display for MouseButton {
     }
 }
 
-#[cfg(feature = "serde")]
 impl std::str::FromStr for MouseButton {
     type Err = ParseError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
@@ -614,11 +1148,16 @@ impl std::str::FromStr for MouseButton {
         if let Some(k) = mouse_canonical_names_lower().get(&s_lower) {
             return Ok(*k);
         }
-        if let Some(caps) = other_mouse_regex().captures(s) {
-            let v = &caps[1]
-                .parse::<u32>()
-                .map_err(|err| Into::<ParseError>::into(err))?;
-            return Ok(MouseButton::OtherButton(*v));
+        match s_lower.as_str() {
+            "left" => return Ok(MouseButton::LeftButton),
+            "middle" => return Ok(MouseButton::MiddleButton),
+            "right" => return Ok(MouseButton::RightButton),
+            "back" | "x1" => return Ok(MouseButton::X1Button),
+            "forward" | "x2" => return Ok(MouseButton::X2Button),
+            _ => {}
+        }
+        if let Some(code) = parse_code(&s_lower, "mousebutton(") {
+            return Ok(MouseButton::OtherButton(code?));
         }
 
         Err(ParseError::UnknownFormat {
@@ -628,6 +1167,17 @@ impl std::str::FromStr for MouseButton {
     }
 }
 
+// Serialized as the canonical name, so that it reads back with `Deserialize`.
+#[cfg(feature = "serde")]
+impl Serialize for MouseButton {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.canonical_name())
+    }
+}
+
 #[cfg(feature = "serde")]
 impl<'de> Deserialize<'de> for MouseButton {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
@@ -639,6 +1189,153 @@ impl<'de> Deserialize<'de> for MouseButton {
     }
 }
 
+impl Input {
+    pub fn canonical_name(self) -> String {
+        match self {
+            Input::Keybd(key) => key.canonical_name(),
+            Input::Mouse(button) => button.canonical_name(),
+        }
+    }
+}
+
+impl std::fmt::Display for Input {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.canonical_name())
+    }
+}
+
+/// Parses the name of a key or, if no key has it, of a mouse button.
+impl std::str::FromStr for Input {
+    type Err = ParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match (KeybdKey::from_str(s), MouseButton::from_str(s)) {
+            (Ok(key), _) => Ok(Input::Keybd(key)),
+            (_, Ok(button)) => Ok(Input::Mouse(button)),
+            _ => Err(ParseError::UnknownKey { name: s.to_owned() }),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for Input {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.canonical_name())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Input {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        std::str::FromStr::from_str(&s).map_err(Error::custom)
+    }
+}
+
+// Serialized as the names of the flags, like `["LSHIFT", "CAPS_LOCK"]`.
+#[cfg(feature = "serde")]
+impl Serialize for Modifiers {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_seq(self.iter_names().map(|(name, _)| name))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Modifiers {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Vec::<String>::deserialize(deserializer)?
+            .iter()
+            .map(|name| {
+                Modifiers::from_name(name)
+                    .ok_or_else(|| Error::custom(format!("unknown modifier '{name}'")))
+            })
+            .collect()
+    }
+}
+
+// Serialized flat, like `{"time": 1697712345.123, "event": "press", "key": "a", ...}`, with
+// the time in seconds since the Unix epoch.
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+struct InputEventRepr {
+    time: f64,
+    event: KeyEvent,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    key: Option<KeybdKey>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    button: Option<MouseButton>,
+    #[serde(default)]
+    modifiers: Modifiers,
+    #[serde(default)]
+    injected: bool,
+    #[serde(default)]
+    device: Option<String>,
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for InputEvent {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let (key, button) = match self.input {
+            Input::Keybd(key) => (Some(key), None),
+            Input::Mouse(button) => (None, Some(button)),
+        };
+        InputEventRepr {
+            time: self
+                .timestamp
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs_f64(),
+            event: self.event,
+            key,
+            button,
+            modifiers: self.modifiers,
+            injected: self.is_injected,
+            device: self.device.clone(),
+        }
+        .serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for InputEvent {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let repr = InputEventRepr::deserialize(deserializer)?;
+        let input = match (repr.key, repr.button) {
+            (Some(key), None) => Input::Keybd(key),
+            (None, Some(button)) => Input::Mouse(button),
+            _ => return Err(Error::custom("expected either a key or a button")),
+        };
+        let timestamp = Duration::try_from_secs_f64(repr.time)
+            .map(|time| std::time::UNIX_EPOCH + time)
+            .map_err(Error::custom)?;
+        Ok(InputEvent {
+            input,
+            event: repr.event,
+            is_injected: repr.injected,
+            modifiers: repr.modifiers,
+            timestamp,
+            device: repr.device,
+        })
+    }
+}
+
 pub fn from_keybd_key(k: KeybdKey) -> Option<char> {
     match k {
         KeybdKey::AKey => Some('a'),
@@ -677,6 +1374,11 @@ pub fn from_keybd_key(k: KeybdKey) -> Option<char> {
         KeybdKey::Numpad7Key => Some('7'),
         KeybdKey::Numpad8Key => Some('8'),
         KeybdKey::Numpad9Key => Some('9'),
+        KeybdKey::NumpadPlusKey => Some('+'),
+        KeybdKey::NumpadMinusKey => Some('-'),
+        KeybdKey::NumpadMultiplyKey => Some('*'),
+        KeybdKey::NumpadDivideKey => Some('/'),
+        KeybdKey::NumpadDecimalKey => Some('.'),
         KeybdKey::Numrow0Key => Some('0'),
         KeybdKey::Numrow1Key => Some('1'),
         KeybdKey::Numrow2Key => Some('2'),
@@ -791,6 +1493,38 @@ impl KeySequence<'_> {
     }
 }
 
+/// Sets the timing used to recognize gestures.
+pub fn set_gesture_timing(timing: GestureTiming) {
+    *GESTURE_TIMING.lock().unwrap() = timing;
+}
+
+/// Replaces all key remaps with the given `(from, to)` pairs. See `KeybdKey::remap`.
+pub fn set_remaps<I: IntoIterator<Item = (KeybdKey, KeybdKey)>>(remaps: I) {
+    *REMAPS.lock().unwrap() = remaps.into_iter().collect();
+}
+
+/// Replaces the names returned by `display_name` for the given keys and buttons, for
+/// example with translated names. Keys and buttons which aren't given use the default
+/// English names again.
+pub fn set_display_names<I: IntoIterator<Item = (Input, String)>>(names: I) {
+    *DISPLAY_NAMES.lock().unwrap() = names.into_iter().collect();
+}
+
+/// Sets how dual-role keys decide between their tap and hold key.
+pub fn set_dual_role_options(options: DualRoleOptions) {
+    *DUAL_ROLE_OPTIONS.lock().unwrap() = options;
+}
+
+/// Calls `callback` with every key and mouse button event, including the ones sent by
+/// inputbot itself, in the order they happened. Replaces any previous listener.
+pub fn set_event_listener<F: Fn(&InputEvent) + Send + Sync + 'static>(callback: F) {
+    *EVENT_LISTENER.lock().unwrap() = Some(Arc::new(callback));
+}
+
+pub fn remove_event_listener() {
+    *EVENT_LISTENER.lock().unwrap() = None;
+}
+
 /// Stops `handle_input_events()` (threadsafe)
 pub fn stop_handling_input_events() {
     HANDLE_EVENTS.store(false, Ordering::Relaxed);
@@ -800,7 +1534,66 @@ pub fn stop_handling_input_events() {
 mod tests {
 
     #[test]
-    #[cfg(feature = "serde")]
+    fn injected_events_are_reported_and_ignored() {
+        use crate::{
+            common::{dispatch_keybd_event, lock_global_state},
+            remove_event_listener, set_event_listener, BindOptions, Input, KeyEvent, KeybdKey,
+        };
+        use std::{
+            sync::{mpsc::channel, Mutex},
+            time::Duration,
+        };
+
+        let _lock = lock_global_state();
+        let key = KeybdKey::OtherKey(0xFFFF_0201);
+        let (sender, fired) = channel();
+        let sender = Mutex::new(sender);
+        key.bind_event(move |event| {
+            let _ = sender.lock().unwrap().send(event.is_injected);
+        });
+        key.set_bind_options(BindOptions {
+            ignore_injected: true,
+            ..Default::default()
+        });
+        let (sender, listened) = channel();
+        let sender = Mutex::new(sender);
+        set_event_listener(move |event| {
+            if event.input == Input::Keybd(key) {
+                let _ = sender
+                    .lock()
+                    .unwrap()
+                    .send((event.event, event.is_injected));
+            }
+        });
+
+        dispatch_keybd_event(key, KeyEvent::Press, true, None);
+        dispatch_keybd_event(key, KeyEvent::Release, true, None);
+        dispatch_keybd_event(key, KeyEvent::Press, false, None);
+        dispatch_keybd_event(key, KeyEvent::Release, false, None);
+
+        let timeout = Duration::from_secs(1);
+        let events: Vec<_> = (0..4)
+            .map_while(|_| listened.recv_timeout(timeout).ok())
+            .collect();
+        let first = fired.recv_timeout(timeout);
+        let second = fired.recv_timeout(Duration::from_millis(50));
+        remove_event_listener();
+        key.unbind();
+
+        assert_eq!(
+            events,
+            [
+                (KeyEvent::Press, true),
+                (KeyEvent::Release, true),
+                (KeyEvent::Press, false),
+                (KeyEvent::Release, false),
+            ]
+        );
+        assert_eq!(first, Ok(false));
+        assert!(second.is_err());
+    }
+
+    #[test]
     fn to_string_roundtrips() -> Result<(), Box<dyn std::error::Error>> {
         use crate::{KeybdKey, MouseButton};
         use std::{collections::HashSet, str::FromStr};
@@ -835,7 +1628,6 @@ mod tests {
     }
 
     #[test]
-    #[cfg(feature = "serde")]
     fn canonical_name_roundtrips() -> Result<(), Box<dyn std::error::Error>> {
         use crate::{KeybdKey, MouseButton};
         use std::{collections::HashSet, str::FromStr};
@@ -871,8 +1663,153 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn modifiers_follow_keys_and_locks() {
+        use crate::{
+            common::lock_global_state, set_right_alt_is_altgr, KeybdKey, LockState, Modifiers,
+        };
+
+        assert_eq!(Modifiers::of_key(KeybdKey::LShiftKey), Modifiers::LSHIFT);
+        assert_eq!(Modifiers::of_key(KeybdKey::RSuper), Modifiers::RSUPER);
+        assert_eq!(Modifiers::of_key(KeybdKey::CapsLockKey), Modifiers::empty());
+        assert_eq!(Modifiers::of_key(KeybdKey::AKey), Modifiers::empty());
+
+        let _lock = lock_global_state();
+        assert_eq!(
+            Modifiers::of_key(KeybdKey::RAltKey),
+            Modifiers::RALT | Modifiers::ALTGR
+        );
+        set_right_alt_is_altgr(false);
+        assert_eq!(Modifiers::of_key(KeybdKey::RAltKey), Modifiers::RALT);
+        set_right_alt_is_altgr(true);
+
+        let locks = LockState {
+            caps_lock: true,
+            scroll_lock: true,
+            compose: true,
+            ..LockState::default()
+        };
+        assert_eq!(
+            Modifiers::of_state([KeybdKey::LControlKey, KeybdKey::RShiftKey], locks),
+            Modifiers::LCONTROL | Modifiers::RSHIFT | Modifiers::CAPS_LOCK | Modifiers::SCROLL_LOCK
+        );
+        assert_eq!(
+            Modifiers::of_state([], LockState::default()),
+            Modifiers::empty()
+        );
+
+        let locks = crate::lock_state();
+        let current = Modifiers::current();
+        assert_eq!(current.contains(Modifiers::CAPS_LOCK), locks.caps_lock);
+        assert_eq!(current.contains(Modifiers::NUM_LOCK), locks.num_lock);
+        assert_eq!(current.contains(Modifiers::SCROLL_LOCK), locks.scroll_lock);
+    }
+
+    #[test]
+    fn modifiers_are_looked_up_by_name() {
+        use crate::Modifiers;
+
+        assert_eq!(Modifiers::from_name("LSHIFT"), Some(Modifiers::LSHIFT));
+        assert_eq!(
+            Modifiers::from_name("CAPS_LOCK"),
+            Some(Modifiers::CAPS_LOCK)
+        );
+        assert_eq!(
+            Modifiers::from_name("CONTROL"),
+            Some(Modifiers::LCONTROL | Modifiers::RCONTROL)
+        );
+        assert_eq!(Modifiers::from_name("lshift"), None);
+        assert_eq!(Modifiers::from_name("Ctrl"), None);
+        for (name, modifier) in Modifiers::all().iter_names() {
+            assert_eq!(Modifiers::from_name(name), Some(modifier));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serialize_roundtrips() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::{KeybdKey, MouseButton};
+        use serde::{Deserialize, Serialize};
+        use strum::IntoEnumIterator;
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Config {
+            key: KeybdKey,
+            button: MouseButton,
+        }
+
+        let keys = KeybdKey::iter().chain([0, 42, u64::MAX].map(KeybdKey::OtherKey));
+        let buttons = MouseButton::iter().chain([0, 42, u32::MAX].map(MouseButton::OtherButton));
+        for (key, button) in keys.zip(buttons.cycle()) {
+            let config = Config { key, button };
+            let serialized = toml::to_string(&config)?;
+            assert_eq!(
+                serialized,
+                format!(
+                    "key = \"{}\"\nbutton = \"{}\"\n",
+                    key.canonical_name(),
+                    button.canonical_name()
+                )
+            );
+            assert_eq!(toml::from_str::<Config>(&serialized)?, config);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn display_names_are_looked_up() {
+        use crate::{common::lock_global_state, set_display_names, Input, KeybdKey, MouseButton};
+        use strum::IntoEnumIterator;
+
+        let _lock = lock_global_state();
+        assert_eq!(KeybdKey::LShiftKey.display_name(), "Left Shift");
+        assert_eq!(KeybdKey::Numpad5Key.display_name(), "Num 5");
+        assert_eq!(KeybdKey::PageUpKey.display_name(), "Page Up");
+        assert_eq!(KeybdKey::AKey.display_name(), "A");
+        assert_eq!(MouseButton::X1Button.display_name(), "Back Button");
+
+        for key in KeybdKey::iter() {
+            assert_eq!(KeybdKey::from_display_name(&key.display_name()), Some(key));
+        }
+        for button in MouseButton::iter() {
+            assert_eq!(
+                MouseButton::from_display_name(&button.display_name()),
+                Some(button)
+            );
+        }
+
+        set_display_names([(Input::Keybd(KeybdKey::SpaceKey), "Leertaste".to_owned())]);
+        assert_eq!(KeybdKey::SpaceKey.display_name(), "Leertaste");
+        assert_eq!(
+            KeybdKey::from_display_name("leertaste"),
+            Some(KeybdKey::SpaceKey)
+        );
+        set_display_names([]);
+        assert_eq!(KeybdKey::SpaceKey.display_name(), "Space");
+    }
+
     #[test]
     #[cfg(feature = "serde")]
+    fn input_events_serialize_roundtrip() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::{Input, InputEvent, KeyEvent, KeybdKey, Modifiers};
+        use std::time::{Duration, UNIX_EPOCH};
+
+        let event = InputEvent {
+            input: Input::Keybd(KeybdKey::AKey),
+            event: KeyEvent::Repeat,
+            is_injected: true,
+            modifiers: Modifiers::LSHIFT | Modifiers::CAPS_LOCK,
+            timestamp: UNIX_EPOCH + Duration::from_millis(1_697_712_345_500),
+            device: Some("keyboard".to_owned()),
+        };
+        let json = serde_json::to_string(&event)?;
+        assert!(json.contains(r#""key":"a""#) && json.contains(r#"["LSHIFT","CAPS_LOCK"]"#));
+        assert!(json.contains(r#""time":1697712345.5"#));
+        assert_eq!(serde_json::from_str::<InputEvent>(&json)?, event);
+        Ok(())
+    }
+
+    #[test]
     fn serialization_case_insensitive() {
         use crate::{KeybdKey, MouseButton};
         use std::{collections::HashSet, str::FromStr};
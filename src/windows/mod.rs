@@ -1,4 +1,4 @@
-use crate::{common::*, public::*};
+use crate::{common::*, keycodes::*, physical::*, public::*};
 use once_cell::sync::Lazy;
 use std::{
     ffi::{c_int, c_short, c_ulong, c_ushort},
@@ -10,11 +10,11 @@ use windows::Win32::{
     Foundation::{LPARAM, LRESULT, WPARAM},
     UI::{
         Input::KeyboardAndMouse::{
-            GetAsyncKeyState, GetKeyState, MapVirtualKeyW, SendInput, INPUT, INPUT_0,
-            INPUT_KEYBOARD, INPUT_MOUSE, KEYBDINPUT, KEYBD_EVENT_FLAGS, KEYEVENTF_KEYUP,
-            KEYEVENTF_SCANCODE, MAP_VIRTUAL_KEY_TYPE, MOUSEEVENTF_HWHEEL, MOUSEEVENTF_LEFTDOWN,
-            MOUSEEVENTF_LEFTUP, MOUSEEVENTF_MIDDLEDOWN, MOUSEEVENTF_MIDDLEUP,
-            MOUSEEVENTF_RIGHTDOWN, MOUSEEVENTF_RIGHTUP, MOUSEEVENTF_WHEEL, MOUSEINPUT,
+            GetAsyncKeyState, GetKeyState, SendInput, INPUT, INPUT_0, INPUT_KEYBOARD, INPUT_MOUSE,
+            KEYBDINPUT, KEYBD_EVENT_FLAGS, KEYEVENTF_EXTENDEDKEY, KEYEVENTF_KEYUP,
+            KEYEVENTF_SCANCODE, MOUSEEVENTF_HWHEEL, MOUSEEVENTF_LEFTDOWN, MOUSEEVENTF_LEFTUP,
+            MOUSEEVENTF_MIDDLEDOWN, MOUSEEVENTF_MIDDLEUP, MOUSEEVENTF_RIGHTDOWN,
+            MOUSEEVENTF_RIGHTUP, MOUSEEVENTF_WHEEL, MOUSEEVENTF_XDOWN, MOUSEEVENTF_XUP, MOUSEINPUT,
             MOUSE_EVENT_FLAGS, VIRTUAL_KEY,
         },
         WindowsAndMessaging::{
@@ -32,22 +32,38 @@ mod inputs;
 
 static KEYBD_HHOOK: Lazy<AtomicPtr<HHOOK>> = Lazy::new(AtomicPtr::default);
 static MOUSE_HHOOK: Lazy<AtomicPtr<HHOOK>> = Lazy::new(AtomicPtr::default);
+static KEYS_DOWN: Lazy<Mutex<HashSet<KeybdKey>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+// Passed as `dwExtraInfo` with all input sent by inputbot, so the hooks can recognize it.
+const INJECTED_EXTRA_INFO: usize = 0x1B07_1B07;
 
 impl KeybdKey {
     /// Returns true if a given `KeybdKey` is currently pressed (in the down position).
     pub fn is_pressed(self) -> bool {
-        (unsafe { GetAsyncKeyState(u64::from(self) as i32) } >> 15) != 0
+        let down = |code: u64| (unsafe { GetAsyncKeyState(code as i32) } >> 15) != 0;
+        match self {
+            KeybdKey::OtherKey(code) => down(code),
+            // Enter and Numpad Enter share a code, which only the keyboard hook tells apart.
+            // Without the hook, either of them counts as Enter.
+            KeybdKey::EnterKey | KeybdKey::NumpadEnterKey
+                if !KEYBD_HHOOK.load(Ordering::Relaxed).is_null() =>
+            {
+                KEYS_DOWN.lock().unwrap().contains(&self)
+            }
+            // Some keys have more than one code.
+            _ => own_virtual_keys(self).any(down),
+        }
     }
 
     /// Presses a given `KeybdKey`. Note: this means the key will remain in the down
     /// position. You must manually call release to create a full 'press'.
     pub fn press(self) {
-        send_keybd_input(KEYEVENTF_SCANCODE, self);
+        send_keybd_input(KEYBD_EVENT_FLAGS(0), self);
     }
 
     /// Releases a given `KeybdKey`. This means the key would be in the up position.
     pub fn release(self) {
-        send_keybd_input(KEYEVENTF_SCANCODE | KEYEVENTF_KEYUP, self);
+        send_keybd_input(KEYEVENTF_KEYUP, self);
     }
 
     /// Returns true if a keyboard key which supports toggling (ScrollLock, NumLock,
@@ -57,6 +73,50 @@ impl KeybdKey {
     }
 }
 
+/// Returns the keys which are currently pressed, see `KeybdKey::is_pressed`.
+pub fn pressed_keys() -> Vec<KeybdKey> {
+    let mut keys: Vec<KeybdKey> = (0x08..=0xFEu64)
+        // VK_SHIFT, VK_CONTROL and VK_MENU duplicate their left and right keys.
+        .filter(|code| !(0x10..=0x12).contains(code))
+        .map(KeybdKey::from)
+        .filter(|key| key.is_pressed())
+        .collect();
+    // Keys with more than one code follow each other.
+    keys.dedup();
+    if KeybdKey::NumpadEnterKey.is_pressed() {
+        keys.push(KeybdKey::NumpadEnterKey);
+    }
+    keys
+}
+
+/// Returns the mouse buttons which are currently pressed, see `MouseButton::is_pressed`.
+pub fn pressed_buttons() -> Vec<MouseButton> {
+    [
+        MouseButton::LeftButton,
+        MouseButton::RightButton,
+        MouseButton::MiddleButton,
+        MouseButton::X1Button,
+        MouseButton::X2Button,
+    ]
+    .into_iter()
+    .filter(|button| button.is_pressed())
+    .collect()
+}
+
+/// Returns which lock states are on. Windows has no compose state, so `compose` is
+/// always false.
+pub fn lock_state() -> LockState {
+    let on = |key: u64| unsafe { GetKeyState(key as i32) & 1 != 0 };
+    LockState {
+        caps_lock: on(u64::from(KeybdKey::CapsLockKey)),
+        num_lock: on(u64::from(KeybdKey::NumLockKey)),
+        scroll_lock: on(u64::from(KeybdKey::ScrollLockKey)),
+        compose: false,
+        // VK_KANA
+        kana: on(0x15),
+    }
+}
+
 impl MouseButton {
     /// Returns true if a given `MouseButton` is currently pressed (in the down position).
     pub fn is_pressed(self) -> bool {
@@ -70,6 +130,9 @@ impl MouseButton {
             MouseButton::LeftButton => send_mouse_input(MOUSEEVENTF_LEFTDOWN, 0, 0, 0),
             MouseButton::RightButton => send_mouse_input(MOUSEEVENTF_RIGHTDOWN, 0, 0, 0),
             MouseButton::MiddleButton => send_mouse_input(MOUSEEVENTF_MIDDLEDOWN, 0, 0, 0),
+            // XBUTTON1 and XBUTTON2
+            MouseButton::X1Button => send_mouse_input(MOUSEEVENTF_XDOWN, 1, 0, 0),
+            MouseButton::X2Button => send_mouse_input(MOUSEEVENTF_XDOWN, 2, 0, 0),
             _ => {}
         }
     }
@@ -80,6 +143,8 @@ impl MouseButton {
             MouseButton::LeftButton => send_mouse_input(MOUSEEVENTF_LEFTUP, 0, 0, 0),
             MouseButton::RightButton => send_mouse_input(MOUSEEVENTF_RIGHTUP, 0, 0, 0),
             MouseButton::MiddleButton => send_mouse_input(MOUSEEVENTF_MIDDLEUP, 0, 0, 0),
+            MouseButton::X1Button => send_mouse_input(MOUSEEVENTF_XUP, 1, 0, 0),
+            MouseButton::X2Button => send_mouse_input(MOUSEEVENTF_XUP, 2, 0, 0),
             _ => {}
         }
     }
@@ -100,11 +165,15 @@ impl MouseCursor {
     }
 
     /// Moves the mouse to a given position based on absolute coordinates. The top left
-    /// corner of the screen is (0, 0).
+    /// corner of the screen is (0, 0). Does nothing if the cursor can't be positioned,
+    /// see `try_move_abs`.
     pub fn move_abs(x: i32, y: i32) {
-        unsafe {
-            SetCursorPos(x, y).unwrap();
-        }
+        let _ = Self::try_move_abs(x, y);
+    }
+
+    /// Moves the mouse like `move_abs`, or returns why it can't.
+    pub fn try_move_abs(x: i32, y: i32) -> Result<(), CursorError> {
+        unsafe { SetCursorPos(x, y) }.map_err(|err| CursorError::Other(err.to_string()))
     }
 }
 
@@ -122,10 +191,10 @@ impl MouseWheel {
 
 /// Starts listening for bound input events (otionally stopping when binds are removed).
 pub fn handle_input_events(auto_stop: bool) {
-    if !MOUSE_BINDS.lock().unwrap().is_empty() {
+    if mouse_bound() {
         set_hook(WH_MOUSE_LL, &MOUSE_HHOOK, mouse_proc);
     };
-    if !KEYBD_BINDS.lock().unwrap().is_empty() {
+    if keybd_bound() {
         set_hook(WH_KEYBOARD_LL, &KEYBD_HHOOK, keybd_proc);
     };
 
@@ -141,48 +210,29 @@ pub fn handle_input_events(auto_stop: bool) {
 }
 
 unsafe extern "system" fn keybd_proc(code: c_int, w_param: WPARAM, l_param: LPARAM) -> LRESULT {
-    if KEYBD_BINDS.lock().unwrap().is_empty() {
+    if !keybd_bound() {
         unset_hook(&KEYBD_HHOOK);
-    } else if w_param.0 as u32 == WM_KEYDOWN || w_param.0 as u32 == WM_SYSKEYDOWN {
-        if let Some(bind) = KEYBD_BINDS
-            .lock()
-            .unwrap()
-            .get_mut(&KeybdKey::from(u64::from(
-                (*(l_param.0 as *const KBDLLHOOKSTRUCT)).vkCode,
-            )))
-        {
-            match bind {
-                Bind::Normal(cb) => {
-                    let cb = Arc::clone(cb);
-                    spawn(move || cb());
-                }
-                Bind::Release(_) => {}
-                Bind::Block(cb) => {
-                    let cb = Arc::clone(cb);
-                    spawn(move || cb());
-                    return LRESULT(1);
-                }
-                Bind::Blockable(cb) => {
-                    if let BlockInput::Block = cb() {
-                        return LRESULT(1);
-                    }
-                }
+    } else {
+        let kbd = &*(l_param.0 as *const KBDLLHOOKSTRUCT);
+        // LLKHF_EXTENDED
+        let key = key_of_virtual_key(u64::from(kbd.vkCode), kbd.flags.0 & 0x01 != 0);
+        let event = match w_param.0 as u32 {
+            // The low level hook reports autorepeat as further key down messages.
+            WM_KEYDOWN | WM_SYSKEYDOWN => Some(if KEYS_DOWN.lock().unwrap().insert(key) {
+                KeyEvent::Press
+            } else {
+                KeyEvent::Repeat
+            }),
+            WM_KEYUP | WM_SYSKEYUP => {
+                KEYS_DOWN.lock().unwrap().remove(&key);
+                Some(KeyEvent::Release)
             }
-        }
-    } else if w_param.0 as u32 == WM_KEYUP || w_param.0 as u32 == WM_SYSKEYUP {
-        if let Some(bind) = KEYBD_RELEASE_BINDS
-            .lock()
-            .unwrap()
-            .get_mut(&KeybdKey::from(u64::from(
-                (*(l_param.0 as *const KBDLLHOOKSTRUCT)).vkCode,
-            )))
-        {
-            match bind {
-                Bind::Release(cb) => {
-                    let cb = Arc::clone(cb);
-                    spawn(move || cb());
-                }
-                Bind::Normal(_) | Bind::Block(_) | Bind::Blockable(_) => {}
+            _ => None,
+        };
+        if let Some(event) = event {
+            let injected = kbd.dwExtraInfo == INJECTED_EXTRA_INFO;
+            if let BlockInput::Block = dispatch_keybd_event(key, event, injected, None) {
+                return LRESULT(1);
             }
         }
     }
@@ -202,38 +252,18 @@ fn hiword_signed(l: DWORD) -> c_short {
 }
 
 unsafe extern "system" fn mouse_proc(code: c_int, w_param: WPARAM, l_param: LPARAM) -> LRESULT {
-    if MOUSE_BINDS.lock().unwrap().is_empty() {
+    if !mouse_bound() {
         unset_hook(&MOUSE_HHOOK);
-    } else if let Some((event, key_up)) = identify_event(w_param, l_param) {
-        if key_up {
-            if let Some(bind) = MOUSE_RELEASE_BINDS.lock().unwrap().get_mut(&event) {
-                match bind {
-                    Bind::Release(cb) => {
-                        let cb = Arc::clone(cb);
-                        spawn(move || cb());
-                    }
-                    Bind::Normal(_) | Bind::Block(_) | Bind::Blockable(_) => {}
-                }
-            }
-        } else if let Some(bind) = MOUSE_BINDS.lock().unwrap().get_mut(&event) {
-            match bind {
-                Bind::Normal(cb) => {
-                    let cb = Arc::clone(cb);
-                    spawn(move || cb());
-                }
-                Bind::Block(cb) => {
-                    let cb = Arc::clone(cb);
-                    spawn(move || cb());
-                    return LRESULT(1);
-                }
-                Bind::Blockable(cb) => {
-                    if let BlockInput::Block = cb() {
-                        return LRESULT(1);
-                    }
-                }
-                Bind::Release(_) => {}
-            }
+    } else if let Some((button, key_up)) = identify_event(w_param, l_param) {
+        let event = if key_up {
+            KeyEvent::Release
+        } else {
+            KeyEvent::Press
         };
+        let injected = (*(l_param.0 as *const MSLLHOOKSTRUCT)).dwExtraInfo == INJECTED_EXTRA_INFO;
+        if let BlockInput::Block = dispatch_mouse_event(button, event, injected, None) {
+            return LRESULT(1);
+        }
     }
     return CallNextHookEx(None, code, w_param, l_param);
 
@@ -304,7 +334,7 @@ fn send_mouse_input(flags: MOUSE_EVENT_FLAGS, data: i32, dx: i32, dy: i32) {
         mouseData: data,
         dwFlags: flags,
         time: 0,
-        dwExtraInfo: 0,
+        dwExtraInfo: INJECTED_EXTRA_INFO,
     };
 
     let mut input_u: INPUT_0 = unsafe { std::mem::zeroed() };
@@ -319,14 +349,24 @@ fn send_mouse_input(flags: MOUSE_EVENT_FLAGS, data: i32, dx: i32, dy: i32) {
 }
 
 fn send_keybd_input(flags: KEYBD_EVENT_FLAGS, key_code: KeybdKey) {
-    let keybd: KEYBDINPUT = unsafe {
-        KEYBDINPUT {
-            wVk: VIRTUAL_KEY(0),
-            wScan: MapVirtualKeyW(u64::from(key_code) as u32, MAP_VIRTUAL_KEY_TYPE(0)) as u16,
-            dwFlags: flags,
-            time: 0,
-            dwExtraInfo: 0,
-        }
+    // Keys are sent by scancode, with the extended flag for keys like Numpad Enter which
+    // share their virtual-key code. Keys without a scancode, like Pause, are sent by
+    // virtual-key code.
+    let (vk, scan, flags) = match key_code.physical_key().and_then(PhysicalKey::scancode) {
+        Some((scancode, true)) => (
+            0,
+            scancode,
+            flags | KEYEVENTF_SCANCODE | KEYEVENTF_EXTENDEDKEY,
+        ),
+        Some((scancode, false)) => (0, scancode, flags | KEYEVENTF_SCANCODE),
+        None => (u64::from(key_code) as u16, 0, flags),
+    };
+    let keybd: KEYBDINPUT = KEYBDINPUT {
+        wVk: VIRTUAL_KEY(vk),
+        wScan: scan,
+        dwFlags: flags,
+        time: 0,
+        dwExtraInfo: INJECTED_EXTRA_INFO,
     };
 
     // We need an "empty" winapi struct to union-ize